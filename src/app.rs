@@ -1,57 +1,146 @@
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command as ProcessCommand, ExitStatus, Output};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, SystemTime};
 
-use crate::command::{Command, CommandHandler, InputHandler};
-use crate::editor::{EditorEnum, NullEdtior};
-use crate::file_explorer::FileExplorer;
+use crate::background::BackgroundTasks;
+use crate::binding::{find_conflicts, get_bindings, Key};
+use crate::command::{begin_input, Command, CommandHandler, InputHandler};
+use crate::config::Config;
+use crate::editor::{editor_kind_for, open_action_for, Editor, EditorKind, NullEdtior, OpenAction};
+use crate::editor_tabs::EditorTabs;
+use crate::file_explorer::{ExplorerTask, FileExplorer};
+use crate::hooks::{file_command, get_hooks, Hook, HookKeys};
 use crate::legend::Legend;
-use crate::text_editor::TextEditor;
+use crate::modal::Modal;
+use crate::modal_variants::{ConfirmationVariant, InfoVariant, OptionsVariant};
+use crate::quick_look::QuickLook;
+use crate::session::Session;
+use crate::sort_entries::SORT_ENTRIES;
 use crate::window::{Drawable, Focusable};
-use anyhow::{Context, Result};
-use crossterm::event::KeyCode;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::widgets::Paragraph;
 use ratatui::Frame;
+use unicode_width::UnicodeWidthStr;
 
 pub struct App {
     pub explorer: FileExplorer,
-    editors: [EditorEnum; 3],
+    editors: [Box<dyn Editor>; 3],
     info_message: Option<String>,
+    notification: Option<Notification>,
     pub legend: Legend,
     pub should_stop: bool,
+    pub external_process: Option<ProcessCommand>,
+    confirm_quit: bool,
+    read_only: bool,
+    open_associations: HashMap<String, String>,
+    quick_look: Option<QuickLook>,
+    recent_files: Vec<PathBuf>,
+    hook_keys: HookKeys,
+    background: BackgroundTasks,
+
+    modal: Modal,
+    quit_prompt_open: bool,
+    sender: Sender<AppTask>,
+    receiver: Receiver<AppTask>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Pane {
+    Explorer,
+    Editor,
+    Split,
+}
+
+pub enum AppTask {
+    Quit,
+    SaveAllAndQuit,
+    OpenRecent(usize),
+    HookFinished(&'static Hook, io::Result<Output>),
+}
+
+const NOTIFICATION_DURATION: Duration = Duration::from_secs(2);
+const RECENT_FILES_LIMIT: usize = 9;
+
+fn running(hook: &Hook) -> String {
+    format!("Running {}", hook.name)
+}
+
+pub struct Notification {
+    message: String,
+    expires_at: SystemTime,
 }
 
-pub fn log(text: &str) -> Result<()> {
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("log.txt")
-        .context("failed to open log file")?;
-    file.write_all(format!("{}\n", text).as_bytes())
-        .context("failed to write to log file")?;
-    Ok(())
+impl Notification {
+    fn new(message: String) -> Self {
+        Self {
+            message,
+            expires_at: SystemTime::now() + NOTIFICATION_DURATION,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        SystemTime::now() >= self.expires_at
+    }
 }
 
 impl App {
-    pub fn new() -> Result<App> {
-        let explorer = FileExplorer::new("explorer", true)?;
+    pub fn new(config: Config) -> Result<App> {
+        App::with_state(config, std::env::current_dir()?, Session::load())
+    }
+
+    // Opens `current_dir` and restores `session`, leaving the process working
+    // directory and the session file alone.
+    fn with_state(config: Config, current_dir: PathBuf, session: Option<Session>) -> Result<App> {
+        let explorer = FileExplorer::new("explorer", true, &config, current_dir.clone())?;
 
-        let editors = [
-            EditorEnum::PreviewExplorer(FileExplorer::new("preview_explorer", false)?),
-            EditorEnum::TextEditor(TextEditor::new()),
-            EditorEnum::NullEdtior(NullEdtior {
+        let editors: [Box<dyn Editor>; 3] = [
+            Box::new(FileExplorer::new(
+                "preview_explorer",
+                false,
+                &config,
+                current_dir,
+            )?),
+            Box::new(EditorTabs::new(&config)),
+            Box::new(NullEdtior {
                 message: Option::None,
             }),
         ];
 
+        let (sender, receiver) = channel();
+
+        let mut modal = Modal::new(Box::new(InfoVariant::new(String::new())));
+        modal.close();
+
         let mut app = App {
             explorer,
             editors,
-            legend: Legend::new(),
+            legend: Legend::new(&config),
             should_stop: false,
+            external_process: None,
             info_message: None,
+            notification: None,
+            confirm_quit: config.confirm_quit,
+            read_only: config.read_only,
+            open_associations: config.open.clone(),
+            quick_look: None,
+            recent_files: Vec::new(),
+            hook_keys: HookKeys::new(get_hooks()),
+            background: BackgroundTasks::default(),
+            modal,
+            quit_prompt_open: false,
+            sender,
+            receiver,
         };
 
-        log("app started")?;
+        log::info!("app started");
+        app.restore_session(session);
+        app.report_binding_conflicts();
 
         app.explorer.focus();
         app.on_selected_file_change();
@@ -75,18 +164,84 @@ impl App {
         self.draw_editor(f, top_layout[1]);
 
         self.legend.draw(f, main_layout[1]);
+        self.draw_background_tasks(f, main_layout[1]);
+        self.draw_notification(f, main_layout[1]);
+
+        if let Some(quick_look) = &self.quick_look {
+            quick_look.draw(f, f.size());
+        }
+
+        if self.modal.is_open() {
+            self.modal.draw(f, f.size());
+        }
+    }
+
+    pub fn update(&mut self) {
+        while let Ok(task) = self.receiver.try_recv() {
+            self.dispatch_on_task(task);
+        }
+        self.explorer.poll_tasks();
+        if let Some(message) = self.explorer.take_notification() {
+            self.notify(message);
+        }
+    }
+
+    fn restore_session(&mut self, session: Option<Session>) {
+        let Some(session) = session else {
+            return;
+        };
+        if let Some(index) = SORT_ENTRIES.iter().position(|e| e.name == session.sort) {
+            let _ = self.explorer.apply_task(ExplorerTask::Sort(index));
+        }
+        if !session.filter.is_empty() {
+            let _ = self
+                .explorer
+                .apply_task(ExplorerTask::Filter(session.filter));
+        }
+        self.recent_files = session
+            .recent_files
+            .into_iter()
+            .filter(|path| path.is_file())
+            .take(RECENT_FILES_LIMIT)
+            .collect();
+    }
+
+    pub fn save_session(&self) {
+        let session = Session {
+            sort: self.explorer.sort_name().to_string(),
+            filter: self.explorer.name_filter().to_string(),
+            recent_files: self.recent_files.clone(),
+        };
+        if let Err(e) = session.save() {
+            log::warn!("could not save session: {}", e);
+        }
+    }
+
+    fn report_binding_conflicts(&mut self) {
+        let conflicts = find_conflicts(get_bindings());
+        for conflict in &conflicts {
+            log::warn!(
+                "{} and {} are both bound to {:?}",
+                conflict.first,
+                conflict.second,
+                conflict.keys
+            );
+        }
+        if !conflicts.is_empty() {
+            self.notify(format!("{} conflicting key bindings", conflicts.len()));
+        }
     }
 
     pub fn on_selected_file_change(&mut self) {
+        let show_hidden = self.explorer.show_hidden();
+        self.editors[EditorKind::PreviewExplorer as usize].set_show_hidden(show_hidden);
         let file_option = self.explorer.get_selected_file();
 
         if let Some(selected_file) = file_option {
-            if let Err(x) = self.provide_editor_mut().set_path(selected_file) {
+            if let Err(x) = self.provide_editor_mut().set_path(selected_file.clone()) {
+                log::warn!("could not open {}: {}", selected_file.display(), x);
                 self.info_message = Some(x.to_string());
-                match self.provide_editor_mut() {
-                    EditorEnum::NullEdtior(editor) => editor.message = Some(x.to_string()),
-                    _ => {}
-                }
+                self.provide_editor_mut().set_message(x.to_string());
             } else {
                 self.info_message = None;
                 let path = self.explorer.get_selected_file().unwrap().clone();
@@ -96,105 +251,437 @@ impl App {
     }
 
     fn on_window_change(&mut self) {
-        let commands_data: Vec<(&str, &str)> = if self.provide_editor_mut().is_focused() {
-            self.provide_editor_mut().get_commands_data()
+        let commands_data: Vec<(&str, &str)> = if self.provide_editor().is_focused() {
+            self.provide_editor().get_commands_data()
         } else {
-            self.explorer
-                .get_commands()
-                .iter()
-                .map(|c| (c.id, c.name))
-                .collect()
+            let mut commands_data = self.explorer.get_commands_data();
+            commands_data.extend(
+                get_hooks()
+                    .iter()
+                    .filter(|_| !self.read_only)
+                    .filter(|hook| get_bindings().iter().any(|b| b.command_id == hook.id))
+                    .map(|hook| (hook.id, hook.name)),
+            );
+            commands_data
         };
 
         self.legend.update_command_bindings(commands_data);
     }
 
+    fn run_hook(&mut self, hook: &'static Hook) {
+        let Some(file) = self.explorer.get_selected_file() else {
+            return;
+        };
+
+        let mut process = hook.process(&file);
+        if hook.interactive {
+            self.external_process = Some(process);
+            return;
+        }
+        self.background.start(running(hook));
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(AppTask::HookFinished(hook, process.output()));
+        });
+    }
+
+    fn on_hook_finished(&mut self, hook: &Hook, output: io::Result<Output>) {
+        self.background.finish(&running(hook));
+        match output {
+            Ok(output) if output.status.success() => self.notify(format!("{} finished", hook.name)),
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                log::warn!("{} failed: {}", hook.id, stderr);
+                self.notify(format!("{} failed ({})", hook.name, output.status));
+            }
+            Err(e) => self.notify(format!("{} failed: {}", hook.name, e)),
+        }
+        self.refresh_explorer();
+    }
+
+    pub fn on_external_process_exit(&mut self, status: io::Result<ExitStatus>) {
+        match status {
+            Ok(status) if !status.success() => {
+                self.notify(format!("Command exited with {}", status))
+            }
+            Err(e) => self.notify(format!("Could not run command: {}", e)),
+            Ok(_) => {}
+        }
+        self.refresh_explorer();
+    }
+
+    fn refresh_explorer(&mut self) {
+        let filter = self.explorer.name_filter().to_string();
+        let _ = self.explorer.apply_task(ExplorerTask::Filter(filter));
+        self.on_selected_file_change();
+    }
+
     fn quit(&mut self, _: KeyCode) -> bool {
+        let unsaved_files = self.unsaved_files();
+        let sender = self.sender.clone();
+        if !unsaved_files.is_empty() {
+            self.modal = Modal::new(Box::new(OptionsVariant::new(
+                format!("Unsaved changes in {}.", unsaved_files.join(", ")),
+                vec![
+                    "Save all and quit".to_string(),
+                    "Discard all and quit".to_string(),
+                    "Cancel".to_string(),
+                ],
+                Box::new(move |option| {
+                    let task = match option {
+                        0 => AppTask::SaveAllAndQuit,
+                        1 => AppTask::Quit,
+                        _ => return,
+                    };
+                    sender.send(task).unwrap();
+                }),
+            )));
+            self.quit_prompt_open = true;
+        } else if self.confirm_quit {
+            self.modal = Modal::new(Box::new(ConfirmationVariant::new(
+                "Quit?".to_string(),
+                Box::new(move |_| {
+                    sender.send(AppTask::Quit).unwrap();
+                }),
+            )));
+            self.quit_prompt_open = true;
+        } else {
+            self.should_stop = true;
+        }
+        true
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.interrupt();
+        } else {
+            self.handle_input(key.into());
+        }
+    }
+
+    // A second interrupt while the quit prompt is open quits without saving.
+    fn interrupt(&mut self) {
+        if self.modal.is_open() && self.quit_prompt_open {
+            self.should_stop = true;
+        } else {
+            self.quit(KeyCode::Null);
+        }
+    }
+
+    pub fn force_quit(&mut self, _: KeyCode) -> bool {
         self.should_stop = true;
         true
     }
 
+    fn unsaved_files(&self) -> Vec<String> {
+        self.editors
+            .iter()
+            .flat_map(|editor| editor.unsaved_files())
+            .collect()
+    }
+
+    fn dispatch_on_task(&mut self, task: AppTask) {
+        match task {
+            AppTask::Quit => self.should_stop = true,
+            AppTask::SaveAllAndQuit => {
+                self.editors.iter_mut().for_each(|editor| editor.save_all());
+                // Files that could not be saved keep the app open with their prompt.
+                self.should_stop = self.unsaved_files().is_empty();
+            }
+            AppTask::OpenRecent(index) => self.open_recent(index),
+            AppTask::HookFinished(hook, output) => self.on_hook_finished(hook, output),
+        }
+    }
+
+    fn record_recent(&mut self, path: &Path) {
+        let Ok(path) = path.canonicalize() else {
+            return;
+        };
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(RECENT_FILES_LIMIT);
+    }
+
+    fn recent_files(&mut self, _: KeyCode) -> bool {
+        self.recent_files.retain(|path| path.is_file());
+        if self.recent_files.is_empty() {
+            self.notify("No recent files".to_string());
+            return true;
+        }
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(OptionsVariant::new(
+            "Recent files".to_string(),
+            self.recent_files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+            Box::new(move |option| {
+                sender.send(AppTask::OpenRecent(option)).unwrap();
+            }),
+        )));
+        self.quit_prompt_open = false;
+        true
+    }
+
+    fn open_recent(&mut self, index: usize) {
+        let Some(path) = self.recent_files.get(index).cloned() else {
+            return;
+        };
+        if let Err(e) = self.explorer.reveal(&path) {
+            self.notify(e.to_string());
+            return;
+        }
+        self.focus_pane(Pane::Explorer);
+        self.on_selected_file_change();
+        if self.explorer.get_selected_file().as_ref() == Some(&path) {
+            self.open_selected_file(KeyCode::Null);
+        }
+        self.on_window_change();
+    }
+
     fn open_selected_file(&mut self, _: KeyCode) -> bool {
-        let file_option = self.explorer.get_selected_file();
-        if let Some(selected_path) = file_option {
-            if !selected_path.is_dir() && self.info_message.is_none() {
-                self.explorer.unfocus();
-                self.provide_editor_mut().focus();
+        let Some(selected_path) = self.explorer.get_selected_file() else {
+            return true;
+        };
+        match open_action_for(&selected_path, &self.open_associations) {
+            OpenAction::Navigate => {}
+            OpenAction::Edit => {
+                if self.info_message.is_none() {
+                    self.focus_pane(self.editor_pane());
+                    if !selected_path.is_dir() {
+                        self.record_recent(&selected_path);
+                    }
+                }
+            }
+            OpenAction::Preview => {
+                self.quick_look(KeyCode::Null);
+            }
+            OpenAction::External(command) => {
+                if self.read_only {
+                    self.notify("Read-only mode: external commands are disabled".to_string());
+                } else {
+                    self.external_process = Some(file_command(&command, &selected_path));
+                }
             }
         }
         true
     }
 
-    fn go_back(&mut self, _: KeyCode) -> bool {
-        self.provide_editor_mut().unfocus();
-        self.explorer.focus();
+    fn open_in_new_tab(&mut self, _: KeyCode) -> bool {
+        if let Some(selected_path) = self.explorer.get_selected_file() {
+            if self.editor_kind() == EditorKind::TextEditor {
+                self.record_recent(&selected_path);
+                if let Err(e) = self.provide_editor_mut().open_in_new_tab(selected_path) {
+                    self.notify(e.to_string());
+                }
+            }
+        }
         true
     }
 
-    fn provide_editor_mut(&mut self) -> &mut EditorEnum {
-        if let Some(_) = self.info_message {
-            &mut self.editors[2]
+    fn quick_look(&mut self, _: KeyCode) -> bool {
+        if let Some(selected_path) = self.explorer.get_selected_file() {
+            match QuickLook::open(&selected_path) {
+                Ok(quick_look) => self.quick_look = Some(quick_look),
+                Err(e) => self.notify(format!("Could not preview: {}", e)),
+            }
+        }
+        true
+    }
+
+    fn focusable_panes(&self) -> Vec<Pane> {
+        if self.editor_kind() == EditorKind::Null {
+            vec![Pane::Explorer]
+        } else if self.provide_editor().has_split() {
+            vec![Pane::Explorer, Pane::Editor, Pane::Split]
         } else {
-            let editor = if let Some(path) = self.explorer.get_selected_file() {
-                if path.is_dir() {
-                    &mut self.editors[0]
-                } else {
-                    &mut self.editors[1]
-                }
-            } else {
-                &mut self.editors[2]
-            };
-            editor
+            vec![Pane::Explorer, Pane::Editor]
         }
     }
 
-    fn provide_editor(&self) -> &EditorEnum {
-        if let Some(_) = self.info_message {
-            &self.editors[2]
+    fn focused_pane(&self) -> Pane {
+        if self.provide_editor().is_focused() {
+            self.editor_pane()
         } else {
-            if let Some(path) = self.explorer.get_selected_file() {
-                if path.is_dir() {
-                    &self.editors[0]
-                } else {
-                    &self.editors[1]
-                }
-            } else {
-                &self.editors[2]
+            Pane::Explorer
+        }
+    }
+
+    // The editor side that shows the explorer selection.
+    fn editor_pane(&self) -> Pane {
+        if self.provide_editor().split_active() {
+            Pane::Split
+        } else {
+            Pane::Editor
+        }
+    }
+
+    fn focus_pane(&mut self, pane: Pane) {
+        match pane {
+            Pane::Explorer => {
+                self.provide_editor_mut().unfocus();
+                self.explorer.focus();
+            }
+            Pane::Editor | Pane::Split => {
+                self.explorer.unfocus();
+                let editor = self.provide_editor_mut();
+                editor.set_split_active(pane == Pane::Split);
+                editor.focus();
             }
         }
     }
 
+    fn cycle_focus_by(&mut self, forward: bool) {
+        let panes = self.focusable_panes();
+        let current = panes
+            .iter()
+            .position(|pane| *pane == self.focused_pane())
+            .unwrap_or(0);
+        let next = if forward {
+            (current + 1) % panes.len()
+        } else {
+            (current + panes.len() - 1) % panes.len()
+        };
+        self.focus_pane(panes[next]);
+    }
+
+    fn cycle_focus(&mut self, _: KeyCode) -> bool {
+        self.cycle_focus_by(true);
+        true
+    }
+
+    fn cycle_focus_back(&mut self, _: KeyCode) -> bool {
+        self.cycle_focus_by(false);
+        true
+    }
+
+    fn go_back(&mut self, _: KeyCode) -> bool {
+        self.focus_pane(Pane::Explorer);
+        true
+    }
+
+    fn editor_kind(&self) -> EditorKind {
+        if self.info_message.is_some() {
+            return EditorKind::Null;
+        }
+        match self.explorer.get_selected_file() {
+            Some(path) => editor_kind_for(&path),
+            None => EditorKind::Null,
+        }
+    }
+
+    fn provide_editor_mut(&mut self) -> &mut dyn Editor {
+        let kind = self.editor_kind();
+        self.editors[kind as usize].as_mut()
+    }
+
+    fn provide_editor(&self) -> &dyn Editor {
+        self.editors[self.editor_kind() as usize].as_ref()
+    }
+
     fn draw_editor(&self, f: &mut Frame, area: Rect) {
         self.provide_editor().draw(f, area)
     }
+
+    fn draw_background_tasks(&self, f: &mut Frame, area: Rect) {
+        let tasks: Vec<&str> = self
+            .explorer
+            .background_tasks()
+            .into_iter()
+            .chain(self.background.descriptions())
+            .collect();
+        if tasks.is_empty() {
+            return;
+        }
+        let text = format!(" {} {} ", self.legend.spinner_frame(), tasks.join(", "));
+        let width = area.width.saturating_sub(2).min(text.width() as u16);
+        let line_area = Rect::new(area.right().saturating_sub(width + 1), area.y, width, 1);
+        f.render_widget(
+            Paragraph::new(text).style(Style::new().black().on_cyan()),
+            line_area,
+        );
+    }
+
+    fn draw_notification(&self, f: &mut Frame, area: Rect) {
+        if let Some(notification) = &self.notification {
+            if notification.is_expired() {
+                return;
+            }
+            let text = format!(" {} ", notification.message);
+            let line_area = Rect::new(
+                area.x + 1,
+                area.y,
+                area.width.saturating_sub(2).min(text.width() as u16),
+                1,
+            );
+            let paragraph = Paragraph::new(text).style(Style::new().black().on_yellow());
+            f.render_widget(paragraph, line_area);
+        }
+    }
+
+    fn notify(&mut self, message: String) {
+        self.notification = Some(Notification::new(message));
+    }
 }
 
 impl InputHandler for App {
-    fn handle_input(&mut self, key_code: KeyCode) -> bool {
+    fn handle_input(&mut self, key: Key) -> bool {
+        begin_input();
+        if self.modal.is_open() {
+            self.modal.handle_input(key.code);
+            if let Ok(task) = self.receiver.try_recv() {
+                self.dispatch_on_task(task);
+            }
+            return true;
+        }
+
+        if let Some(quick_look) = &mut self.quick_look {
+            if !quick_look.handle_input(key.code) {
+                self.quick_look = None;
+            }
+            return true;
+        }
+
+        if self.notification.as_ref().is_some_and(|n| n.is_expired()) {
+            self.notification = None;
+        }
+
         let mut captured = false;
         let editor = self.provide_editor_mut();
 
         if editor.is_focused() {
             if editor.modal_open() {
-                captured |= self.provide_editor_mut().handle_input(key_code);
+                captured |= self.provide_editor_mut().handle_input(key);
                 if !captured {
-                    self.go_back(key_code);
+                    self.go_back(key.code);
                 }
             } else {
-                captured |= self.provide_editor_mut().handle_input(key_code);
+                captured |= self.provide_editor_mut().handle_input(key);
             }
         } else if self.explorer.is_focused() {
-            captured |= self.explorer.handle_input(key_code);
+            captured |= self.explorer.handle_input(key);
+            if let Some(message) = self.explorer.take_notification() {
+                self.notify(message);
+            }
+            if let Some(process) = self.explorer.take_external_process() {
+                self.external_process = Some(process);
+            }
             if captured {
                 self.on_selected_file_change();
             }
         }
         if !captured {
-            captured |= self.handle_command(key_code);
+            captured |= self.handle_command(key);
             if captured {
                 self.on_window_change();
             }
         }
+        if !captured && self.explorer.is_focused() && !self.read_only {
+            captured |= self.hook_keys.handle_command(key);
+            if let Some(hook) = self.hook_keys.take_triggered() {
+                self.run_hook(hook);
+            }
+        }
         captured
     }
 }
@@ -203,18 +690,48 @@ impl CommandHandler for App {
     fn get_name(&self) -> &'static str {
         "app"
     }
-    fn get_commands(&self) -> Vec<Command<App>> {
-        vec![
+    fn get_commands(&self) -> &'static [Command<App>] {
+        &[
             Command {
                 id: "app.quit",
                 name: "Quit",
                 func: App::quit,
             },
+            Command {
+                id: "app.force_quit",
+                name: "Force quit",
+                func: App::force_quit,
+            },
             Command {
                 id: "app.go_back",
                 name: "Back",
                 func: App::go_back,
             },
+            Command {
+                id: "app.cycle_focus",
+                name: "Next pane",
+                func: App::cycle_focus,
+            },
+            Command {
+                id: "app.cycle_focus_back",
+                name: "Previous pane",
+                func: App::cycle_focus_back,
+            },
+            Command {
+                id: "app.recent_files",
+                name: "Recent files",
+                func: App::recent_files,
+            },
+            Command {
+                id: "app.quick_look",
+                name: "Quick look",
+                func: App::quick_look,
+            },
+            Command {
+                id: "app.open_in_new_tab",
+                name: "Open in new tab",
+                func: App::open_in_new_tab,
+            },
             Command {
                 id: "app.open_selected_file",
                 name: "Open file",
@@ -223,3 +740,152 @@ impl CommandHandler for App {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn ctrl_c() -> KeyEvent {
+        KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)
+    }
+
+    // An app browsing a fresh temporary directory, with no saved session.
+    fn app_in(dir: &TempDir) -> App {
+        App::with_state(Config::default(), dir.path().to_path_buf(), None).unwrap()
+    }
+
+    fn app_with_unsaved_changes() -> (TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "text").unwrap();
+        let mut app = app_in(&dir);
+        let editor = &mut app.editors[EditorKind::TextEditor as usize];
+        editor.set_path(path).unwrap();
+        editor.handle_input(KeyCode::Char('i').into());
+        editor.handle_input(KeyCode::Char('x').into());
+        assert_eq!(app.unsaved_files(), ["file.txt"]);
+        (dir, app)
+    }
+
+    fn app_showing_a_text_file() -> (TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "text").unwrap();
+        let app = app_in(&dir);
+        (dir, app)
+    }
+
+    #[test]
+    fn ctrl_c_quits_right_away_without_unsaved_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_in(&dir);
+
+        app.handle_key_event(ctrl_c());
+
+        assert!(app.should_stop);
+    }
+
+    #[test]
+    fn ctrl_c_asks_about_unsaved_changes_and_a_second_one_quits() {
+        let (_dir, mut app) = app_with_unsaved_changes();
+
+        app.handle_key_event(ctrl_c());
+        assert!(app.modal.is_open());
+        assert!(!app.should_stop);
+
+        app.handle_key_event(ctrl_c());
+        assert!(app.should_stop);
+    }
+
+    #[test]
+    fn ctrl_c_in_another_modal_asks_before_quitting() {
+        let (dir, mut app) = app_with_unsaved_changes();
+        app.recent_files = vec![dir.path().join("file.txt")];
+        app.recent_files(KeyCode::Char('O'));
+        assert!(app.modal.is_open());
+
+        app.handle_key_event(ctrl_c());
+
+        assert!(app.modal.is_open());
+        assert!(app.quit_prompt_open);
+        assert!(!app.should_stop);
+    }
+
+    #[test]
+    fn focus_cycles_between_the_explorer_and_the_editor() {
+        let (_dir, mut app) = app_showing_a_text_file();
+        assert_eq!(app.focusable_panes(), [Pane::Explorer, Pane::Editor]);
+        assert_eq!(app.focused_pane(), Pane::Explorer);
+
+        app.cycle_focus(KeyCode::Tab);
+        assert_eq!(app.focused_pane(), Pane::Editor);
+        app.cycle_focus(KeyCode::Tab);
+        assert_eq!(app.focused_pane(), Pane::Explorer);
+        app.cycle_focus_back(KeyCode::BackTab);
+        assert_eq!(app.focused_pane(), Pane::Editor);
+    }
+
+    #[test]
+    fn focus_cycles_through_the_editor_split() {
+        let (_dir, mut app) = app_showing_a_text_file();
+        app.cycle_focus(KeyCode::Tab);
+        app.handle_input(KeyCode::Char('v').into());
+        assert_eq!(
+            app.focusable_panes(),
+            [Pane::Explorer, Pane::Editor, Pane::Split]
+        );
+        assert_eq!(app.focused_pane(), Pane::Split);
+
+        app.cycle_focus(KeyCode::Tab);
+        assert_eq!(app.focused_pane(), Pane::Explorer);
+        app.cycle_focus(KeyCode::Tab);
+        assert_eq!(app.focused_pane(), Pane::Editor);
+        app.cycle_focus(KeyCode::Tab);
+        assert_eq!(app.focused_pane(), Pane::Split);
+
+        app.cycle_focus_back(KeyCode::BackTab);
+        assert_eq!(app.focused_pane(), Pane::Editor);
+        app.cycle_focus_back(KeyCode::BackTab);
+        assert_eq!(app.focused_pane(), Pane::Explorer);
+        app.cycle_focus_back(KeyCode::BackTab);
+        assert_eq!(app.focused_pane(), Pane::Split);
+    }
+
+    #[test]
+    fn cancelling_the_quit_prompt_keeps_the_app_and_its_edits() {
+        let (dir, mut app) = app_with_unsaved_changes();
+
+        app.quit(KeyCode::Char('q'));
+        assert!(app.modal.is_open());
+        app.handle_input(KeyCode::Char('3').into());
+        app.update();
+
+        assert!(!app.modal.is_open());
+        assert!(!app.should_stop);
+        assert_eq!(app.unsaved_files(), ["file.txt"]);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("file.txt")).unwrap(),
+            "text"
+        );
+    }
+
+    #[test]
+    fn a_restored_session_sets_the_sort_filter_and_recent_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "a").unwrap();
+        fs::write(dir.path().join("b.md"), "b").unwrap();
+        let session = Session {
+            sort: "Size".to_string(),
+            filter: ".md".to_string(),
+            recent_files: vec![dir.path().join("a.txt"), dir.path().join("gone.txt")],
+        };
+
+        let app =
+            App::with_state(Config::default(), dir.path().to_path_buf(), Some(session)).unwrap();
+
+        assert_eq!(app.explorer.sort_name(), "Size");
+        assert_eq!(app.explorer.name_filter(), ".md");
+        assert_eq!(app.recent_files, [dir.path().join("a.txt")]);
+    }
+}
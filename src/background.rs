@@ -0,0 +1,42 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// Operations running on worker threads, described for the status bar. Each
+// one polls its token and stops early once it is cancelled.
+#[derive(Default)]
+pub struct BackgroundTasks {
+    tasks: Vec<(String, Arc<AtomicBool>)>,
+}
+
+impl BackgroundTasks {
+    pub fn start(&mut self, description: String) -> Arc<AtomicBool> {
+        let token = Arc::new(AtomicBool::new(false));
+        self.tasks.push((description, token.clone()));
+        token
+    }
+
+    pub fn finish(&mut self, description: &str) {
+        if let Some(index) = self.tasks.iter().position(|(task, _)| task == description) {
+            self.tasks.remove(index);
+        }
+    }
+
+    // Returns whether there was anything left to cancel.
+    pub fn cancel_all(&mut self) -> bool {
+        let mut cancelled = false;
+        for (_, token) in &self.tasks {
+            cancelled |= !token.swap(true, Ordering::Relaxed);
+        }
+        cancelled
+    }
+
+    pub fn descriptions(&self) -> impl Iterator<Item = &str> {
+        self.tasks.iter().map(|(task, _)| task.as_str())
+    }
+}
+
+pub fn is_cancelled(token: &AtomicBool) -> bool {
+    token.load(Ordering::Relaxed)
+}
@@ -1,87 +1,572 @@
-use crossterm::event::KeyCode;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::hooks::get_hooks;
 
 pub struct Binding {
     pub command_id: &'static str,
-    pub key_code: KeyCode,
+    pub keys: Vec<Key>,
+}
+
+// A key together with its modifiers. Shift is folded into the character
+// itself, so `Q` and Shift-q are the same key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                code
+            }
+            _ => code,
+        };
+        Key { code, modifiers }
+    }
+
+    // Plain keys, or keys with only Shift held, are typed as text.
+    pub fn is_plain(&self) -> bool {
+        !self
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl From<KeyCode> for Key {
+    fn from(code: KeyCode) -> Self {
+        Key::new(code, KeyModifiers::NONE)
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+static BINDINGS: OnceLock<Vec<Binding>> = OnceLock::new();
+
+pub fn get_bindings() -> &'static [Binding] {
+    BINDINGS.get_or_init(default_bindings)
+}
+
+pub fn init_bindings(overrides: &HashMap<String, String>) {
+    let mut bindings = default_bindings();
+    for hook in get_hooks() {
+        match parse_keys(&hook.key) {
+            Some(keys) => bindings.push(Binding {
+                command_id: hook.id,
+                keys,
+            }),
+            None => log::warn!("invalid keys for {}: {}", hook.id, hook.key),
+        }
+    }
+    for (command_id, keys) in overrides {
+        let Some(binding) = bindings.iter_mut().find(|b| b.command_id == command_id) else {
+            log::warn!("unknown command in bindings: {}", command_id);
+            continue;
+        };
+        match parse_keys(keys) {
+            Some(keys) => binding.keys = keys,
+            None => log::warn!("invalid keys for {}: {}", command_id, keys),
+        }
+    }
+    let _ = BINDINGS.set(bindings);
+}
+
+fn parse_keys(text: &str) -> Option<Vec<Key>> {
+    let keys: Option<Vec<Key>> = text.split_whitespace().map(parse_key).collect();
+    keys.filter(|keys| !keys.is_empty())
+}
+
+// Parses a key such as `q`, `Enter`, `Ctrl-q` or `A-Left`.
+fn parse_key(text: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text;
+    while let Some((modifier, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "c" | "ctrl" => KeyModifiers::CONTROL,
+            "a" | "m" | "alt" => KeyModifiers::ALT,
+            "s" | "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = key;
+    }
+    Some(Key::new(parse_key_code(rest)?, modifiers))
 }
 
-pub fn get_bindings() -> Vec<Binding> {
+fn parse_key_code(text: &str) -> Option<KeyCode> {
+    let mut chars = text.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let key_code = match text.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        other => KeyCode::F(other.strip_prefix('f')?.parse().ok()?),
+    };
+    Some(key_code)
+}
+
+fn default_bindings() -> Vec<Binding> {
     vec![
         Binding {
             command_id: "app.quit",
-            key_code: KeyCode::Char('q'),
+            keys: vec![KeyCode::Char('q').into()],
+        },
+        Binding {
+            command_id: "app.force_quit",
+            keys: vec![KeyCode::Char('Q').into()],
         },
         Binding {
             command_id: "app.open_selected_file",
-            key_code: KeyCode::Enter,
+            keys: vec![KeyCode::Enter.into()],
         },
         Binding {
             command_id: "app.go_back",
-            key_code: KeyCode::Esc,
+            keys: vec![KeyCode::Esc.into()],
+        },
+        Binding {
+            command_id: "app.open_in_new_tab",
+            keys: vec![KeyCode::Char('o').into()],
+        },
+        Binding {
+            command_id: "app.recent_files",
+            keys: vec![KeyCode::Char('O').into()],
+        },
+        Binding {
+            command_id: "app.quick_look",
+            keys: vec![KeyCode::Char(' ').into()],
+        },
+        Binding {
+            command_id: "app.cycle_focus",
+            keys: vec![KeyCode::Tab.into()],
+        },
+        Binding {
+            command_id: "app.cycle_focus_back",
+            keys: vec![KeyCode::BackTab.into()],
+        },
+        Binding {
+            command_id: "explorer.select_previous_file",
+            keys: vec![KeyCode::Char('k').into()],
+        },
+        Binding {
+            command_id: "explorer.select_next_file",
+            keys: vec![KeyCode::Char('j').into()],
         },
         Binding {
             command_id: "explorer.select_previous_file",
-            key_code: KeyCode::Char('k'),
+            keys: vec![KeyCode::Up.into()],
         },
         Binding {
             command_id: "explorer.select_next_file",
-            key_code: KeyCode::Char('j'),
+            keys: vec![KeyCode::Down.into()],
+        },
+        Binding {
+            command_id: "explorer.next_sibling_dir",
+            keys: vec![KeyCode::Char(']').into()],
+        },
+        Binding {
+            command_id: "explorer.prev_sibling_dir",
+            keys: vec![KeyCode::Char('[').into()],
+        },
+        Binding {
+            command_id: "explorer.history_back",
+            keys: vec![Key::new(KeyCode::Left, KeyModifiers::ALT)],
+        },
+        Binding {
+            command_id: "explorer.history_back",
+            keys: vec![KeyCode::Char('H').into()],
+        },
+        Binding {
+            command_id: "explorer.history_forward",
+            keys: vec![Key::new(KeyCode::Right, KeyModifiers::ALT)],
+        },
+        Binding {
+            command_id: "explorer.history_forward",
+            keys: vec![KeyCode::Char('L').into()],
+        },
+        Binding {
+            command_id: "explorer.ancestor_up",
+            keys: vec![KeyCode::Char('u').into()],
         },
         Binding {
             command_id: "explorer.open_selected_file",
-            key_code: KeyCode::Enter,
+            keys: vec![KeyCode::Enter.into()],
         },
         Binding {
             command_id: "explorer.delete_current_file",
-            key_code: KeyCode::Char('d'),
+            keys: vec![KeyCode::Char('d').into()],
         },
         Binding {
             command_id: "explorer.move_current_file",
-            key_code: KeyCode::Char('m'),
+            keys: vec![KeyCode::Char('m').into()],
+        },
+        Binding {
+            command_id: "explorer.rename_current_file",
+            keys: vec![KeyCode::Char('r').into()],
+        },
+        Binding {
+            command_id: "explorer.copy_current_file",
+            keys: vec![KeyCode::Char('c').into()],
+        },
+        Binding {
+            command_id: "explorer.yank",
+            keys: vec![KeyCode::Char('y').into()],
+        },
+        Binding {
+            command_id: "explorer.cut",
+            keys: vec![KeyCode::Char('x').into()],
+        },
+        Binding {
+            command_id: "explorer.paste",
+            keys: vec![KeyCode::Char('p').into()],
         },
         Binding {
             command_id: "explorer.sort_entries",
-            key_code: KeyCode::Char('s'),
+            keys: vec![KeyCode::Char('s').into()],
+        },
+        Binding {
+            command_id: "explorer.toggle_sort_direction",
+            keys: vec![KeyCode::Char('S').into()],
+        },
+        Binding {
+            command_id: "explorer.toggle_dirs_first",
+            keys: vec![KeyCode::Char('D').into()],
         },
         Binding {
             command_id: "explorer.create_file",
-            key_code: KeyCode::Char('c'),
+            keys: vec![KeyCode::Char('a').into()],
+        },
+        Binding {
+            command_id: "explorer.create_and_enter",
+            keys: vec![KeyCode::Char('A').into()],
+        },
+        Binding {
+            command_id: "explorer.new_from_template",
+            keys: vec![KeyCode::Char('T').into()],
+        },
+        Binding {
+            command_id: "explorer.run_command",
+            keys: vec![KeyCode::Char('!').into()],
         },
         Binding {
             command_id: "explorer.filter",
-            key_code: KeyCode::Char('/'),
+            keys: vec![KeyCode::Char('/').into()],
+        },
+        Binding {
+            command_id: "explorer.toggle_timestamp_format",
+            keys: vec![KeyCode::Char('t').into()],
+        },
+        Binding {
+            command_id: "explorer.toggle_hidden",
+            keys: vec![KeyCode::Char('.').into()],
+        },
+        Binding {
+            command_id: "explorer.item_count",
+            keys: vec![KeyCode::Char('i').into()],
+        },
+        Binding {
+            command_id: "explorer.cancel_background",
+            keys: vec![KeyCode::Char('X').into()],
+        },
+        Binding {
+            command_id: "explorer.toggle_view",
+            keys: vec![KeyCode::Char('v').into()],
+        },
+        Binding {
+            command_id: "explorer.select_left",
+            keys: vec![KeyCode::Char('h').into()],
+        },
+        Binding {
+            command_id: "explorer.select_right",
+            keys: vec![KeyCode::Char('l').into()],
+        },
+        Binding {
+            command_id: "explorer.select_left",
+            keys: vec![KeyCode::Left.into()],
+        },
+        Binding {
+            command_id: "explorer.select_right",
+            keys: vec![KeyCode::Right.into()],
+        },
+        Binding {
+            command_id: "explorer.goto_target",
+            keys: vec![KeyCode::Char('g').into(), KeyCode::Char('l').into()],
+        },
+        Binding {
+            command_id: "explorer.copy_path",
+            keys: vec![KeyCode::Char('Y').into(), KeyCode::Char('p').into()],
+        },
+        Binding {
+            command_id: "explorer.copy_name",
+            keys: vec![KeyCode::Char('Y').into(), KeyCode::Char('n').into()],
+        },
+        Binding {
+            command_id: "explorer.copy_relative_path",
+            keys: vec![KeyCode::Char('Y').into(), KeyCode::Char('r').into()],
         },
         Binding {
             command_id: "explorer.go_back",
-            key_code: KeyCode::Esc,
+            keys: vec![KeyCode::Esc.into()],
+        },
+        Binding {
+            command_id: "editor.toggle_split",
+            keys: vec![KeyCode::Char('v').into()],
+        },
+        Binding {
+            command_id: "editor.next_tab",
+            keys: vec![KeyCode::Char(']').into()],
+        },
+        Binding {
+            command_id: "editor.prev_tab",
+            keys: vec![KeyCode::Char('[').into()],
+        },
+        Binding {
+            command_id: "editor.close_tab",
+            keys: vec![KeyCode::Char('x').into()],
+        },
+        Binding {
+            command_id: "preview_explorer.scroll_up",
+            keys: vec![KeyCode::Char('k').into()],
+        },
+        Binding {
+            command_id: "preview_explorer.scroll_down",
+            keys: vec![KeyCode::Char('j').into()],
+        },
+        Binding {
+            command_id: "preview_explorer.page_up",
+            keys: vec![KeyCode::PageUp.into()],
+        },
+        Binding {
+            command_id: "preview_explorer.page_down",
+            keys: vec![KeyCode::PageDown.into()],
         },
         Binding {
             command_id: "text_editor.next_char",
-            key_code: KeyCode::Char('l'),
+            keys: vec![KeyCode::Char('l').into()],
         },
         Binding {
             command_id: "text_editor.prev_char",
-            key_code: KeyCode::Char('h'),
+            keys: vec![KeyCode::Char('h').into()],
         },
         Binding {
             command_id: "text_editor.next_line",
-            key_code: KeyCode::Char('j'),
+            keys: vec![KeyCode::Char('j').into()],
         },
         Binding {
             command_id: "text_editor.prev_line",
-            key_code: KeyCode::Char('k'),
+            keys: vec![KeyCode::Char('k').into()],
         },
         Binding {
             command_id: "text_editor.save",
-            key_code: KeyCode::Char('s'),
+            keys: vec![KeyCode::Char('s').into()],
         },
         Binding {
             command_id: "text_editor.insert_mode",
-            key_code: KeyCode::Char('i'),
+            keys: vec![KeyCode::Char('i').into()],
+        },
+        Binding {
+            command_id: "text_editor.undo",
+            keys: vec![KeyCode::Char('u').into()],
+        },
+        Binding {
+            command_id: "text_editor.redo",
+            keys: vec![KeyCode::Char('U').into()],
+        },
+        Binding {
+            command_id: "text_editor.goto_line",
+            keys: vec![KeyCode::Char(':').into()],
+        },
+        Binding {
+            command_id: "text_editor.search",
+            keys: vec![KeyCode::Char('/').into()],
+        },
+        Binding {
+            command_id: "text_editor.next_match",
+            keys: vec![KeyCode::Char('n').into()],
+        },
+        Binding {
+            command_id: "text_editor.prev_match",
+            keys: vec![KeyCode::Char('N').into()],
+        },
+        Binding {
+            command_id: "text_editor.replace",
+            keys: vec![KeyCode::Char('R').into()],
+        },
+        Binding {
+            command_id: "text_editor.encoding",
+            keys: vec![KeyCode::Char('E').into()],
+        },
+        Binding {
+            command_id: "text_editor.reload",
+            keys: vec![KeyCode::Char('e').into()],
+        },
+        Binding {
+            command_id: "text_editor.indent",
+            keys: vec![KeyCode::Char('>').into()],
+        },
+        Binding {
+            command_id: "text_editor.dedent",
+            keys: vec![KeyCode::Char('<').into()],
+        },
+        Binding {
+            command_id: "text_editor.toggle_comment",
+            keys: vec![KeyCode::Char('g').into(), KeyCode::Char('c').into()],
+        },
+        Binding {
+            command_id: "text_editor.stats",
+            keys: vec![KeyCode::Char('S').into()],
+        },
+        Binding {
+            command_id: "text_editor.toggle_wrap",
+            keys: vec![KeyCode::Char('w').into()],
+        },
+        Binding {
+            command_id: "text_editor.toggle_line_numbers",
+            keys: vec![KeyCode::Char('#').into()],
         },
         Binding {
             command_id: "text_editor.go_back",
-            key_code: KeyCode::Esc,
+            keys: vec![KeyCode::Esc.into()],
         },
     ]
 }
+
+pub struct BindingConflict {
+    pub first: &'static str,
+    pub second: &'static str,
+    pub keys: Vec<Key>,
+}
+
+fn scope(command_id: &str) -> &str {
+    command_id.split('.').next().unwrap_or(command_id)
+}
+
+pub fn find_conflicts(bindings: &[Binding]) -> Vec<BindingConflict> {
+    let mut conflicts = Vec::new();
+    for (i, first) in bindings.iter().enumerate() {
+        for second in &bindings[i + 1..] {
+            if (first.keys.starts_with(&second.keys) || second.keys.starts_with(&first.keys))
+                && first.command_id != second.command_id
+                && scope(first.command_id) == scope(second.command_id)
+            {
+                conflicts.push(BindingConflict {
+                    first: first.command_id,
+                    second: second.command_id,
+                    keys: first.keys.clone(),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_reads_modifiers() {
+        assert_eq!(
+            parse_key("Ctrl-q"),
+            Some(Key::new(KeyCode::Char('q'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            parse_key("c-A-left"),
+            Some(Key::new(
+                KeyCode::Left,
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            ))
+        );
+        assert_eq!(parse_key("-"), Some(KeyCode::Char('-').into()));
+        assert_eq!(parse_key("Hyper-q"), None);
+        assert_eq!(parse_key("Ctrl-"), None);
+    }
+
+    #[test]
+    fn shift_is_folded_into_characters() {
+        assert_eq!(parse_key("Shift-q"), Some(KeyCode::Char('Q').into()));
+        let event = KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT);
+        assert_eq!(Key::from(event), KeyCode::Char('Q').into());
+        let event = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(Key::from(event), KeyCode::BackTab.into());
+    }
+
+    #[test]
+    fn parse_keys_reads_chords() {
+        let z = Key::from(KeyCode::Char('Z'));
+        assert_eq!(parse_keys("Z Z"), Some(vec![z, z]));
+        assert_eq!(
+            parse_keys("Ctrl-x s"),
+            Some(vec![
+                Key::new(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                KeyCode::Char('s').into()
+            ])
+        );
+        assert_eq!(parse_keys("  "), None);
+        assert_eq!(parse_keys("q nope"), None);
+    }
+
+    #[test]
+    fn modified_keys_do_not_conflict_with_plain_ones() {
+        let bindings = [
+            Binding {
+                command_id: "app.quit",
+                keys: parse_keys("Ctrl-q").unwrap(),
+            },
+            Binding {
+                command_id: "app.force_quit",
+                keys: parse_keys("q").unwrap(),
+            },
+        ];
+        assert!(find_conflicts(&bindings).is_empty());
+    }
+
+    #[test]
+    fn default_bindings_do_not_conflict() {
+        let conflicts: Vec<_> = find_conflicts(&default_bindings())
+            .into_iter()
+            .map(|conflict| (conflict.first, conflict.second))
+            .collect();
+        assert!(conflicts.is_empty(), "{conflicts:?}");
+    }
+
+    #[test]
+    fn requested_default_keys_are_kept() {
+        let bindings = default_bindings();
+        let keys_for = |command_id| {
+            bindings
+                .iter()
+                .find(|binding| binding.command_id == command_id)
+                .map(|binding| binding.keys.clone())
+        };
+        assert_eq!(keys_for("explorer.copy_current_file"), parse_keys("c"));
+        assert_eq!(keys_for("explorer.create_file"), parse_keys("a"));
+        assert_eq!(keys_for("explorer.yank"), parse_keys("y"));
+        assert_eq!(keys_for("explorer.copy_path"), parse_keys("Y p"));
+    }
+}
@@ -0,0 +1,22 @@
+use std::cell::RefCell;
+
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+
+thread_local! {
+    // On X11 the copied text is only served while the clipboard is alive.
+    static CLIPBOARD: RefCell<Option<Clipboard>> = const { RefCell::new(None) };
+}
+
+pub fn copy(text: &str) -> Result<()> {
+    CLIPBOARD.with_borrow_mut(|clipboard| {
+        if clipboard.is_none() {
+            *clipboard = Some(Clipboard::new()?);
+        }
+        clipboard
+            .as_mut()
+            .ok_or_else(|| anyhow!("clipboard unavailable"))?
+            .set_text(text)?;
+        Ok(())
+    })
+}
@@ -1,6 +1,20 @@
+use std::cell::RefCell;
+
 use crossterm::event::KeyCode;
 
-use crate::binding::get_bindings;
+use crate::binding::{get_bindings, Key};
+
+type PendingKeys = Option<(&'static str, Vec<Key>)>;
+
+thread_local! {
+    static PENDING_KEYS: RefCell<PendingKeys> = const { RefCell::new(None) };
+    static NEXT_PENDING_KEYS: RefCell<PendingKeys> = const { RefCell::new(None) };
+}
+
+pub fn begin_input() {
+    let next = NEXT_PENDING_KEYS.with(|next| next.borrow_mut().take());
+    PENDING_KEYS.with(|pending| *pending.borrow_mut() = next);
+}
 
 pub struct Command<T> {
     pub id: &'static str,
@@ -19,35 +33,69 @@ macro_rules! as_command {
 }
 
 pub trait InputHandler {
-    fn handle_input(&mut self, key_code: KeyCode) -> bool;
+    fn handle_input(&mut self, key: Key) -> bool;
 }
 
-pub trait CommandHandler: Sized {
+pub trait CommandHandler: Sized + 'static {
     fn get_name(&self) -> &'static str;
-    fn get_commands(&self) -> Vec<Command<Self>>;
+    fn get_commands(&self) -> &'static [Command<Self>];
 
-    fn handle_command(&mut self, key_code: KeyCode) -> bool {
-        let name = self.get_name();
-        let bindings = get_bindings();
+    fn is_command_enabled(&self, _command_id: &str) -> bool {
+        true
+    }
+
+    // Runs a bound command that is not in `get_commands`, such as a hook
+    // defined in the config.
+    fn run_unlisted_command(&mut self, _command_id: &'static str) -> bool {
+        false
+    }
 
-        let binding_option = bindings.iter().find(|binding| {
-            let command_id_parts: Vec<&str> = binding.command_id.split(".").collect();
-            let handler_name = command_id_parts.first().unwrap().to_owned();
-            handler_name == name && binding.key_code == key_code
+    fn handle_command(&mut self, key: Key) -> bool {
+        let name = self.get_name();
+        let pending = PENDING_KEYS.with(|pending| match pending.borrow().as_ref() {
+            Some((scope, keys)) if *scope == name => keys.clone(),
+            _ => Vec::new(),
         });
-        if binding_option.is_some() {
-            let binding = binding_option.unwrap();
-            let commands = self.get_commands();
-            let command_id = binding.command_id;
-            let command_option = commands.iter().find(|command| command.id == command_id);
-            if command_option.is_some() {
-                let command = command_option.unwrap();
-                (command.func)(self, binding.key_code)
-            } else {
-                false
+
+        if !pending.is_empty() {
+            let mut keys = pending;
+            keys.push(key);
+            PENDING_KEYS.with(|pending| *pending.borrow_mut() = None);
+            if self.handle_key_sequence(keys) {
+                return true;
+            }
+        }
+        self.handle_key_sequence(vec![key])
+    }
+
+    fn handle_key_sequence(&mut self, keys: Vec<Key>) -> bool {
+        let name = self.get_name();
+        let scoped_bindings = get_bindings()
+            .iter()
+            .filter(|binding| binding.command_id.split('.').next() == Some(name));
+
+        let mut is_prefix = false;
+        for binding in scoped_bindings {
+            if binding.keys == keys {
+                let command_id = binding.command_id;
+                let command_option = self
+                    .get_commands()
+                    .iter()
+                    .find(|command| command.id == command_id);
+                return match command_option {
+                    Some(command) if self.is_command_enabled(command.id) => {
+                        (command.func)(self, keys.last().unwrap().code)
+                    }
+                    Some(_) => false,
+                    None => self.run_unlisted_command(command_id),
+                };
             }
-        } else {
-            false
+            is_prefix |= binding.keys.starts_with(&keys);
+        }
+
+        if is_prefix {
+            NEXT_PENDING_KEYS.with(|next| *next.borrow_mut() = Some((name, keys)));
         }
+        is_prefix
     }
 }
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::hooks::HookConfig;
+use crate::paths;
+use crate::sort_entries::SORT_ENTRIES;
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub confirm_quit: bool,
+    pub read_only: bool,
+    pub confirm_delete: bool,
+    pub confirm_move: bool,
+    pub confirm_overwrite: bool,
+    pub protect_nonempty_dirs: bool,
+    pub default_sort: String,
+    pub case_sensitive_filter: bool,
+    pub filter_scope: FilterScope,
+    pub show_hidden: bool,
+    pub dirs_first: bool,
+    pub relative_timestamps: bool,
+    pub auto_pair: bool,
+    pub tab_width: usize,
+    pub indent_with_tabs: bool,
+    pub line_numbers: bool,
+    pub scroll_off: usize,
+    pub undo_limit: usize,
+    pub relative_root: Option<PathBuf>,
+    pub wrap_sibling_dirs: bool,
+    pub legend_step_ms: u64,
+    pub legend_mode: LegendMode,
+    pub bindings: HashMap<String, String>,
+    pub open: HashMap<String, String>,
+    pub hooks: Vec<HookConfig>,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterScope {
+    Name,
+    Path,
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegendMode {
+    Bounce,
+    Loop,
+    None,
+}
+
+const DIRECTORY_CONFIG_FILE: &str = ".rfm.toml";
+
+#[derive(Deserialize)]
+struct DirectoryConfig {
+    default_sort: Option<String>,
+    case_sensitive_filter: Option<bool>,
+    show_hidden: Option<bool>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            confirm_quit: false,
+            read_only: false,
+            confirm_delete: true,
+            confirm_move: true,
+            confirm_overwrite: true,
+            protect_nonempty_dirs: false,
+            default_sort: String::from("name"),
+            case_sensitive_filter: false,
+            filter_scope: FilterScope::Name,
+            show_hidden: false,
+            dirs_first: true,
+            relative_timestamps: false,
+            auto_pair: true,
+            tab_width: 4,
+            indent_with_tabs: false,
+            line_numbers: true,
+            scroll_off: 3,
+            undo_limit: 100,
+            relative_root: None,
+            wrap_sibling_dirs: true,
+            legend_step_ms: 250,
+            legend_mode: LegendMode::Bounce,
+            bindings: HashMap::new(),
+            open: HashMap::new(),
+            hooks: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let args: Vec<String> = env::args().collect();
+        let mut config = match config_path(&args) {
+            Some(path) => Config::read(&path),
+            None => Config::default(),
+        };
+
+        if args.iter().any(|arg| arg == "--confirm-quit") {
+            config.confirm_quit = true;
+        }
+        if args.iter().any(|arg| arg == "--read-only") {
+            config.read_only = true;
+        }
+        config
+    }
+
+    fn read(path: &Path) -> Config {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Config::default();
+        };
+        toml::from_str(&text).unwrap_or_else(|e| {
+            log::error!("invalid config {}: {}", path.display(), e);
+            Config::default()
+        })
+    }
+
+    pub fn for_directory(&self, dir: &Path) -> Config {
+        match read_directory_config(dir) {
+            Some(Ok(overrides)) => Config {
+                default_sort: overrides.default_sort.unwrap_or(self.default_sort.clone()),
+                case_sensitive_filter: overrides
+                    .case_sensitive_filter
+                    .unwrap_or(self.case_sensitive_filter),
+                show_hidden: overrides.show_hidden.unwrap_or(self.show_hidden),
+                ..self.clone()
+            },
+            Some(Err(e)) => {
+                log::error!(
+                    "invalid config {}: {}",
+                    dir.join(DIRECTORY_CONFIG_FILE).display(),
+                    e
+                );
+                self.clone()
+            }
+            None => self.clone(),
+        }
+    }
+
+    // The sort a `.rfm.toml` in `dir` asks for, if it sets one.
+    pub fn directory_sort(dir: &Path) -> Option<usize> {
+        let default_sort = read_directory_config(dir)?.ok()?.default_sort?;
+        Some(sort_index(&default_sort))
+    }
+
+    pub fn sort_index(&self) -> usize {
+        sort_index(&self.default_sort)
+    }
+}
+
+fn sort_index(name: &str) -> usize {
+    let name = name.to_lowercase();
+    SORT_ENTRIES
+        .iter()
+        .position(|entry| entry.name.to_lowercase().starts_with(&name))
+        .unwrap_or(0)
+}
+
+fn read_directory_config(dir: &Path) -> Option<Result<DirectoryConfig, toml::de::Error>> {
+    let text = fs::read_to_string(dir.join(DIRECTORY_CONFIG_FILE)).ok()?;
+    Some(toml::from_str(&text))
+}
+
+fn config_path(args: &[String]) -> Option<PathBuf> {
+    if let Some(index) = args.iter().position(|arg| arg == "--config") {
+        return args.get(index + 1).map(PathBuf::from);
+    }
+    if let Ok(path) = env::var("RFM_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    Some(paths::config_file())
+}
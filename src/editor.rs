@@ -1,7 +1,7 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Ok, Result};
-use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     widgets::{Block, Paragraph},
@@ -9,85 +9,114 @@ use ratatui::{
 };
 
 use crate::{
-    command::{CommandHandler, InputHandler},
-    file_explorer::FileExplorer,
-    text_editor::TextEditor,
+    binding::Key,
+    command::InputHandler,
     window::{Drawable, Focusable},
 };
 
-pub enum EditorEnum {
-    TextEditor(TextEditor),
-    PreviewExplorer(FileExplorer),
-    NullEdtior(NullEdtior),
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditorKind {
+    PreviewExplorer = 0,
+    TextEditor = 1,
+    Null = 2,
 }
 
-pub trait Editor: Drawable + Focusable + InputHandler {
-    fn set_path(&mut self, path: PathBuf) -> Result<()>;
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileCategory {
+    Directory,
+    File,
 }
 
-impl EditorEnum {
-    pub fn get_editor_mut(&mut self) -> &mut dyn Editor {
-        match self {
-            EditorEnum::TextEditor(editor) => editor,
-            EditorEnum::PreviewExplorer(editor) => editor,
-            EditorEnum::NullEdtior(editor) => editor,
+impl FileCategory {
+    pub fn of(path: &Path) -> Self {
+        if path.is_dir() {
+            FileCategory::Directory
+        } else {
+            FileCategory::File
         }
     }
+}
+
+const EDITOR_ASSOCIATIONS: [(FileCategory, EditorKind); 2] = [
+    (FileCategory::Directory, EditorKind::PreviewExplorer),
+    (FileCategory::File, EditorKind::TextEditor),
+];
+
+pub fn editor_kind_for(path: &Path) -> EditorKind {
+    let category = FileCategory::of(path);
+    EDITOR_ASSOCIATIONS
+        .iter()
+        .find(|(associated, _)| *associated == category)
+        .map_or(EditorKind::Null, |(_, kind)| *kind)
+}
 
-    pub fn get_editor(&self) -> &dyn Editor {
-        match self {
-            EditorEnum::TextEditor(editor) => editor,
-            EditorEnum::PreviewExplorer(editor) => editor,
-            EditorEnum::NullEdtior(editor) => editor,
+#[derive(Clone, PartialEq)]
+pub enum OpenAction {
+    Navigate,
+    Edit,
+    Preview,
+    External(String),
+}
+
+impl OpenAction {
+    fn parse(text: &str) -> Self {
+        match text {
+            "navigate" => OpenAction::Navigate,
+            "edit" => OpenAction::Edit,
+            "preview" => OpenAction::Preview,
+            command => OpenAction::External(command.to_string()),
         }
     }
+}
 
-    pub fn set_path(&mut self, path: PathBuf) -> Result<()> {
-        self.get_editor_mut().set_path(path)
+pub fn open_action_for(path: &Path, associations: &HashMap<String, String>) -> OpenAction {
+    let category = FileCategory::of(path);
+    let key = match category {
+        FileCategory::Directory => Some(String::from("directory")),
+        FileCategory::File => path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase()),
+    };
+    if let Some(action) = key.and_then(|key| associations.get(&key)) {
+        return OpenAction::parse(action);
     }
-
-    pub fn draw(&self, f: &mut Frame, area: Rect) {
-        self.get_editor().draw(f, area)
+    match category {
+        FileCategory::Directory => OpenAction::Navigate,
+        FileCategory::File => OpenAction::Edit,
     }
+}
 
-    pub fn is_focused(&self) -> bool {
-        self.get_editor().is_focused()
-    }
+pub trait Editor: Drawable + Focusable + InputHandler {
+    fn set_path(&mut self, path: PathBuf) -> Result<()>;
+    fn get_commands_data(&self) -> Vec<(&'static str, &'static str)>;
 
-    pub fn focus(&mut self) {
-        self.get_editor_mut().focus()
+    fn open_in_new_tab(&mut self, path: PathBuf) -> Result<()> {
+        self.set_path(path)
     }
 
-    pub fn unfocus(&mut self) {
-        self.get_editor_mut().unfocus()
+    fn modal_open(&self) -> bool {
+        false
     }
 
-    pub fn handle_input(&mut self, key_code: KeyCode) -> bool {
-        self.get_editor_mut().handle_input(key_code)
+    fn has_split(&self) -> bool {
+        false
     }
 
-    pub fn get_commands_data(&self) -> Vec<(&'static str, &'static str)> {
-        match self {
-            EditorEnum::TextEditor(editor) => editor
-                .get_commands()
-                .iter()
-                .map(|c| (c.id, c.name))
-                .collect(),
-            EditorEnum::PreviewExplorer(editor) => editor
-                .get_commands()
-                .iter()
-                .map(|c| (c.id, c.name))
-                .collect(),
-            EditorEnum::NullEdtior(_) => vec![],
-        }
+    fn split_active(&self) -> bool {
+        false
     }
 
-    pub fn modal_open(&self) -> bool {
-        match self {
-            EditorEnum::TextEditor(editor) => editor.modal_open,
-            _ => false,
-        }
+    fn set_split_active(&mut self, _active: bool) {}
+
+    fn set_message(&mut self, _message: String) {}
+
+    fn set_show_hidden(&mut self, _show_hidden: bool) {}
+
+    fn unsaved_files(&self) -> Vec<String> {
+        vec![]
     }
+
+    fn save_all(&mut self) {}
 }
 
 pub struct NullEdtior {
@@ -115,7 +144,7 @@ impl Drawable for NullEdtior {
 }
 
 impl InputHandler for NullEdtior {
-    fn handle_input(&mut self, _: KeyCode) -> bool {
+    fn handle_input(&mut self, _: Key) -> bool {
         false
     }
 }
@@ -134,4 +163,36 @@ impl Editor for NullEdtior {
     fn set_path(&mut self, _: PathBuf) -> Result<()> {
         Ok(())
     }
+
+    fn get_commands_data(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+
+    fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directories_are_previewed_and_files_are_edited() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, "notes").unwrap();
+
+        assert!(editor_kind_for(dir.path()) == EditorKind::PreviewExplorer);
+        assert!(editor_kind_for(&file) == EditorKind::TextEditor);
+    }
+
+    #[test]
+    fn every_category_has_an_editor() {
+        for category in [FileCategory::Directory, FileCategory::File] {
+            assert!(EDITOR_ASSOCIATIONS
+                .iter()
+                .any(|(associated, kind)| *associated == category && *kind != EditorKind::Null));
+        }
+    }
 }
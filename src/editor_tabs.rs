@@ -0,0 +1,507 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use anyhow::Result;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    widgets::Tabs,
+    Frame,
+};
+
+use crate::{
+    as_command,
+    binding::Key,
+    command::{Command, CommandHandler, InputHandler},
+    config::Config,
+    editor::Editor,
+    modal::Modal,
+    modal_variants::{InfoVariant, OptionsVariant},
+    text_editor::TextEditor,
+    window::{Drawable, Focusable},
+};
+
+pub struct EditorTabs {
+    tabs: Vec<TextEditor>,
+    current: usize,
+    split: Option<TextEditor>,
+    split_active: bool,
+    selected_file: Option<PathBuf>,
+    config: Config,
+
+    modal: Modal,
+    sender: Sender<TabsTask>,
+    receiver: Receiver<TabsTask>,
+}
+
+pub enum TabsTask {
+    SaveAndClose(usize),
+    Close(usize),
+}
+
+impl EditorTabs {
+    pub fn new(config: &Config) -> Self {
+        let (sender, receiver) = channel();
+
+        let mut modal = Modal::new(Box::new(InfoVariant::new(String::new())));
+        modal.close();
+        EditorTabs {
+            tabs: vec![TextEditor::new(config)],
+            current: 0,
+            split: None,
+            split_active: false,
+            selected_file: None,
+            config: config.clone(),
+            modal,
+            sender,
+            receiver,
+        }
+    }
+
+    fn current_tab(&self) -> &TextEditor {
+        &self.tabs[self.current]
+    }
+
+    fn current_tab_mut(&mut self) -> &mut TextEditor {
+        &mut self.tabs[self.current]
+    }
+
+    // The buffer that takes input and follows the explorer selection.
+    fn active(&self) -> &TextEditor {
+        match &self.split {
+            Some(split) if self.split_active => split,
+            _ => self.current_tab(),
+        }
+    }
+
+    fn active_mut(&mut self) -> &mut TextEditor {
+        match &mut self.split {
+            Some(split) if self.split_active => split,
+            _ => &mut self.tabs[self.current],
+        }
+    }
+
+    fn find_tab(&self, path: &Path) -> Option<usize> {
+        self.tabs.iter().rposition(|tab| tab.file() == path)
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        if index == self.current {
+            return;
+        }
+        let focused = self.current_tab().is_focused();
+        self.current_tab_mut().unfocus();
+        self.current = index;
+        if focused {
+            self.current_tab_mut().focus();
+        }
+    }
+
+    pub fn next_tab(&mut self) {
+        self.switch_to((self.current + 1) % self.tabs.len());
+    }
+
+    pub fn prev_tab(&mut self) {
+        self.switch_to((self.current + self.tabs.len() - 1) % self.tabs.len());
+    }
+
+    pub fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            return;
+        }
+        let index = self.current;
+        if !self.current_tab().has_unsaved_changes() {
+            self.remove_tab(index);
+            return;
+        }
+
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(OptionsVariant::new(
+            format!(
+                "{} has unsaved changes.",
+                self.current_tab().get_file_name()
+            ),
+            vec![
+                "Save".to_string(),
+                "Discard".to_string(),
+                "Cancel".to_string(),
+            ],
+            Box::new(move |option| {
+                let task = match option {
+                    0 => TabsTask::SaveAndClose(index),
+                    1 => TabsTask::Close(index),
+                    _ => return,
+                };
+                sender.send(task).unwrap();
+            }),
+        )));
+    }
+
+    // Opens the file selected in the explorer beside the current tab, which
+    // stays put while the new side follows the selection.
+    pub fn toggle_split(&mut self) {
+        let focused = self.is_focused();
+        if self.split.take().is_some() {
+            self.split_active = false;
+            if focused {
+                self.current_tab_mut().focus();
+            }
+            return;
+        }
+        let path = self
+            .selected_file
+            .clone()
+            .unwrap_or_else(|| self.current_tab().file().to_path_buf());
+        // The split is only for comparing, so it never holds edits to lose
+        // when it follows the selection or is closed.
+        let config = Config {
+            read_only: true,
+            ..self.config.clone()
+        };
+        let mut split = TextEditor::new(&config);
+        if split.set_path(path).is_ok() {
+            self.split = Some(split);
+            self.set_split_active(true);
+        }
+    }
+
+    fn remove_tab(&mut self, index: usize) {
+        let focused = self.current_tab().is_focused();
+        self.tabs.remove(index);
+        self.current = self.current.min(self.tabs.len() - 1);
+        if focused {
+            self.current_tab_mut().focus();
+        }
+    }
+
+    fn dispatch_on_task(&mut self, task: TabsTask) {
+        match task {
+            TabsTask::SaveAndClose(index) => {
+                if self.tabs[index].write_file() {
+                    self.remove_tab(index);
+                } else {
+                    self.current = index;
+                }
+            }
+            TabsTask::Close(index) => self.remove_tab(index),
+        }
+    }
+
+    fn draw_tab_strip(&self, f: &mut Frame, area: Rect) {
+        let titles: Vec<String> = self
+            .tabs
+            .iter()
+            .map(|tab| {
+                if tab.has_unsaved_changes() {
+                    format!("{}*", tab.get_file_name())
+                } else {
+                    tab.get_file_name().to_string()
+                }
+            })
+            .collect();
+        let tabs = Tabs::new(titles)
+            .select(self.current)
+            .highlight_style(Style::default().bg(Color::Blue));
+
+        f.render_widget(tabs, area);
+    }
+
+    fn draw_buffers(&self, f: &mut Frame, area: Rect) {
+        if let Some(split) = &self.split {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(area);
+            self.current_tab().draw(f, chunks[0]);
+            split.draw(f, chunks[1]);
+        } else {
+            self.current_tab().draw(f, area);
+        }
+    }
+}
+
+impl Drawable for EditorTabs {
+    fn draw(&self, f: &mut Frame, area: Rect) {
+        if self.tabs.len() == 1 {
+            self.draw_buffers(f, area);
+        } else {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Fill(1)])
+                .split(area);
+            self.draw_tab_strip(f, chunks[0]);
+            self.draw_buffers(f, chunks[1]);
+        }
+
+        if self.modal.is_open() {
+            self.modal.draw(f, area);
+        }
+    }
+}
+
+impl Focusable for EditorTabs {
+    fn focus(&mut self) {
+        self.active_mut().focus();
+    }
+
+    fn unfocus(&mut self) {
+        self.current_tab_mut().unfocus();
+        if let Some(split) = &mut self.split {
+            split.unfocus();
+        }
+    }
+
+    fn is_focused(&self) -> bool {
+        self.current_tab().is_focused() || self.split.as_ref().is_some_and(|s| s.is_focused())
+    }
+}
+
+impl InputHandler for EditorTabs {
+    fn handle_input(&mut self, key: Key) -> bool {
+        if self.modal.is_open() {
+            self.modal.handle_input(key.code);
+            if let Ok(task) = self.receiver.try_recv() {
+                self.dispatch_on_task(task);
+            }
+            return true;
+        }
+        self.active_mut().handle_input(key) || self.handle_command(key)
+    }
+}
+
+impl CommandHandler for EditorTabs {
+    fn get_name(&self) -> &'static str {
+        "editor"
+    }
+
+    fn get_commands(&self) -> &'static [Command<EditorTabs>] {
+        &[
+            Command {
+                id: "editor.toggle_split",
+                name: "Split",
+                func: as_command!(EditorTabs, toggle_split),
+            },
+            Command {
+                id: "editor.next_tab",
+                name: "Next tab",
+                func: as_command!(EditorTabs, next_tab),
+            },
+            Command {
+                id: "editor.prev_tab",
+                name: "Prev tab",
+                func: as_command!(EditorTabs, prev_tab),
+            },
+            Command {
+                id: "editor.close_tab",
+                name: "Close tab",
+                func: as_command!(EditorTabs, close_tab),
+            },
+        ]
+    }
+}
+
+impl Editor for EditorTabs {
+    fn set_path(&mut self, path: PathBuf) -> Result<()> {
+        self.selected_file = Some(path.clone());
+        if let Some(split) = self.split.as_mut().filter(|_| self.split_active) {
+            return split.set_path(path);
+        }
+        if let Some(index) = self.find_tab(&path) {
+            self.switch_to(index);
+            return Ok(());
+        }
+        if !self.tabs[0].has_unsaved_changes() {
+            self.switch_to(0);
+            return self.current_tab_mut().set_path(path);
+        }
+        // The first tab follows the selection, but an edited buffer is never
+        // replaced: it stays open as a tab of its own behind a fresh one.
+        let mut tab = TextEditor::new(&self.config);
+        tab.set_path(path)?;
+        self.tabs.insert(0, tab);
+        self.current += 1;
+        self.switch_to(0);
+        Ok(())
+    }
+
+    fn open_in_new_tab(&mut self, path: PathBuf) -> Result<()> {
+        if let Some(index) = self.find_tab(&path).filter(|&index| index > 0) {
+            self.switch_to(index);
+            return Ok(());
+        }
+        let mut tab = TextEditor::new(&self.config);
+        tab.set_path(path)?;
+        self.tabs.push(tab);
+        self.switch_to(self.tabs.len() - 1);
+        Ok(())
+    }
+
+    fn get_commands_data(&self) -> Vec<(&'static str, &'static str)> {
+        let mut commands = self.active().get_commands_data();
+        let tab_commands = self.get_commands();
+        let visible = if self.tabs.len() > 1 {
+            tab_commands.len()
+        } else {
+            1
+        };
+        commands.extend(tab_commands.iter().take(visible).map(|c| (c.id, c.name)));
+        commands
+    }
+
+    fn modal_open(&self) -> bool {
+        self.active().modal_open()
+    }
+
+    fn has_split(&self) -> bool {
+        self.split.is_some()
+    }
+
+    fn split_active(&self) -> bool {
+        self.split.is_some() && self.split_active
+    }
+
+    fn set_split_active(&mut self, active: bool) {
+        if active == self.split_active() || (active && self.split.is_none()) {
+            return;
+        }
+        let focused = self.is_focused();
+        self.unfocus();
+        self.split_active = active;
+        if focused {
+            self.focus();
+        }
+    }
+
+    fn unsaved_files(&self) -> Vec<String> {
+        self.tabs
+            .iter()
+            .chain(&self.split)
+            .flat_map(|tab| tab.unsaved_files())
+            .collect()
+    }
+
+    fn save_all(&mut self) {
+        self.tabs.iter_mut().for_each(|tab| tab.save_all());
+        if let Some(split) = &mut self.split {
+            split.save_all();
+        }
+        // Show the first tab that could not be saved, with its prompt.
+        if let Some(index) = self.tabs.iter().position(|tab| tab.has_unsaved_changes()) {
+            self.current = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyCode;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn files(names: &[&str]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for name in names {
+            fs::write(dir.path().join(name), name).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn the_split_follows_the_selection_beside_the_open_file() {
+        let dir = files(&["a.txt", "b.txt"]);
+        let mut tabs = EditorTabs::new(&Config::default());
+        tabs.set_path(dir.path().join("a.txt")).unwrap();
+        tabs.focus();
+
+        tabs.toggle_split();
+        tabs.set_path(dir.path().join("b.txt")).unwrap();
+
+        assert!(tabs.split_active());
+        assert_eq!(tabs.current_tab().file(), dir.path().join("a.txt"));
+        assert_eq!(tabs.active().file(), dir.path().join("b.txt"));
+        assert!(tabs.active().is_focused());
+        assert!(!tabs.current_tab().is_focused());
+    }
+
+    #[test]
+    fn focus_moves_between_the_sides_of_the_split() {
+        let dir = files(&["a.txt"]);
+        let mut tabs = EditorTabs::new(&Config::default());
+        tabs.set_path(dir.path().join("a.txt")).unwrap();
+        tabs.focus();
+        tabs.toggle_split();
+
+        tabs.set_split_active(false);
+        assert!(tabs.current_tab().is_focused());
+        assert!(!tabs.split.as_ref().unwrap().is_focused());
+
+        tabs.toggle_split();
+        assert!(!tabs.has_split());
+        assert!(tabs.is_focused());
+    }
+
+    #[test]
+    fn selecting_another_file_keeps_unsaved_edits_in_their_own_tab() {
+        let dir = files(&["a.txt", "b.txt"]);
+        let mut tabs = EditorTabs::new(&Config::default());
+        tabs.set_path(dir.path().join("a.txt")).unwrap();
+        tabs.focus();
+        tabs.handle_input(KeyCode::Char('i').into());
+        tabs.handle_input(KeyCode::Char('x').into());
+        tabs.handle_input(KeyCode::Esc.into());
+
+        tabs.set_path(dir.path().join("b.txt")).unwrap();
+
+        assert_eq!(tabs.tabs.len(), 2);
+        assert_eq!(tabs.current_tab().file(), dir.path().join("b.txt"));
+        assert!(tabs.current_tab().is_focused());
+        assert_eq!(tabs.tabs[1].file(), dir.path().join("a.txt"));
+        assert_eq!(tabs.unsaved_files(), ["a.txt"]);
+
+        tabs.set_path(dir.path().join("a.txt")).unwrap();
+        assert_eq!(tabs.current, 1);
+        assert_eq!(tabs.tabs.len(), 2);
+    }
+
+    #[test]
+    fn cancelling_the_close_prompt_keeps_the_tab_and_its_edits() {
+        let dir = files(&["a.txt", "b.txt"]);
+        let mut tabs = EditorTabs::new(&Config::default());
+        tabs.set_path(dir.path().join("a.txt")).unwrap();
+        tabs.open_in_new_tab(dir.path().join("b.txt")).unwrap();
+        tabs.focus();
+        tabs.handle_input(KeyCode::Char('i').into());
+        tabs.handle_input(KeyCode::Char('x').into());
+        tabs.handle_input(KeyCode::Esc.into());
+
+        tabs.close_tab();
+        assert!(tabs.modal.is_open());
+        tabs.handle_input(KeyCode::Char('3').into());
+
+        assert!(!tabs.modal.is_open());
+        assert_eq!(tabs.tabs.len(), 2);
+        assert_eq!(tabs.current_tab().file(), dir.path().join("b.txt"));
+        assert_eq!(tabs.unsaved_files(), ["b.txt"]);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("b.txt")).unwrap(),
+            "b.txt"
+        );
+    }
+
+    #[test]
+    fn the_split_is_read_only() {
+        let dir = files(&["a.txt", "b.txt"]);
+        let mut tabs = EditorTabs::new(&Config::default());
+        tabs.set_path(dir.path().join("a.txt")).unwrap();
+        tabs.focus();
+        tabs.toggle_split();
+        tabs.set_path(dir.path().join("b.txt")).unwrap();
+
+        tabs.handle_input(KeyCode::Char('i').into());
+        tabs.handle_input(KeyCode::Char('x').into());
+
+        assert!(tabs.unsaved_files().is_empty());
+        assert!(!tabs.active().is_command_enabled("text_editor.insert_mode"));
+    }
+}
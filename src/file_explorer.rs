@@ -1,58 +1,135 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use byte_unit::Byte;
+use chrono::{DateTime, Local};
 use crossterm::event::KeyCode;
 use ratatui::{
-    layout::{Constraint, Rect},
-    style::{Color, Style},
-    text::Span,
-    widgets::{Block, Borders, Row, Table, TableState},
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{block::Title, Block, Borders, Cell as TableCell, Paragraph, Row, Table, TableState},
     Frame,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fs,
+    process::Command as ProcessCommand,
+    sync::atomic::AtomicBool,
     sync::mpsc::{channel, Receiver, Sender},
+    time::SystemTime,
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::{
+    background::{is_cancelled, BackgroundTasks},
+    binding::Key,
+    clipboard,
     command::{Command, CommandHandler, InputHandler},
-    editor::Editor,
+    config::{Config, FilterScope},
+    editor::{open_action_for, Editor, OpenAction},
+    hooks::shell_command,
     modal::Modal,
     modal_variants::{ConfirmationVariant, InfoVariant, OptionsVariant, QuestionVariant},
+    paths,
     sort_entries::SORT_ENTRIES,
     window::{Drawable, Focusable},
 };
 
 pub struct FileExplorer {
     pub current_dir: PathBuf,
-    pub selected_index: usize,
     pub entries: Vec<PathBuf>,
     pub table_state: RefCell<TableState>,
     interactive: bool,
     name: &'static str,
+    global_config: Config,
+    config: Config,
 
     modal: Modal,
     name_filter: String,
     current_sort: usize,
+    // A sort set by the current directory's `.rfm.toml`, which wins over
+    // `current_sort` until the user picks another one.
+    directory_sort: Option<usize>,
+    sort_reversed: bool,
+    dirs_first: bool,
+    relative_timestamps: bool,
     is_focused: bool,
+    notification: Option<String>,
+    external_process: Option<ProcessCommand>,
+    scroll_offset: usize,
+    visible_rows: Cell<usize>,
+    compact: bool,
+    ancestor_levels: Option<usize>,
+    grid_rows: Cell<usize>,
+    grid_first_column: Cell<usize>,
+    history: Vec<PathBuf>,
+    history_index: usize,
+    file_clipboard: Option<(PathBuf, ClipboardOp)>,
+    background: BackgroundTasks,
 
     sender: Sender<ExplorerTask>,
     receiver: Receiver<ExplorerTask>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClipboardOp {
+    Cut,
+    Copy,
+}
+
+#[derive(Clone)]
 pub enum ExplorerTask {
     DeleteFile(PathBuf),
+    TypedDelete(PathBuf, String),
+    DeleteProgress(PathBuf, usize),
+    DeleteFinished(PathBuf, Result<usize, String>),
+    DeleteCancelled(PathBuf, usize),
     MoveFile(PathBuf, String),
+    RenameFile(PathBuf, String),
+    CopyFile(PathBuf, String),
+    Paste(PathBuf, ClipboardOp),
+    ConfirmedMove(PathBuf, PathBuf),
     CreateFile(String),
+    CreateAndEnter(String),
+    PickTemplate(PathBuf),
+    CreateFromTemplate(PathBuf, String),
     Sort(usize),
     Filter(String),
+    CountRecursive(PathBuf),
+    CountFinished(PathBuf, (usize, usize), bool),
+    RunCommand(String),
+    ShowInfo(String),
+    CancelBackground,
+}
+
+impl ExplorerTask {
+    fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            ExplorerTask::DeleteFile(_)
+                | ExplorerTask::TypedDelete(..)
+                | ExplorerTask::MoveFile(..)
+                | ExplorerTask::RenameFile(..)
+                | ExplorerTask::CopyFile(..)
+                | ExplorerTask::Paste(..)
+                | ExplorerTask::ConfirmedMove(..)
+                | ExplorerTask::CreateFile(_)
+                | ExplorerTask::CreateAndEnter(_)
+                | ExplorerTask::CreateFromTemplate(..)
+                | ExplorerTask::RunCommand(_)
+        )
+    }
 }
 
 impl FileExplorer {
-    pub fn new(name: &'static str, interactive: bool) -> Result<Self> {
-        let current_dir = std::env::current_dir().unwrap();
-        let entries = read_dir_entries(&current_dir)?;
+    pub fn new(
+        name: &'static str,
+        interactive: bool,
+        config: &Config,
+        current_dir: PathBuf,
+    ) -> Result<Self> {
+        let directory_config = config.for_directory(&current_dir);
+        let directory_sort = Config::directory_sort(&current_dir);
         let list_state = RefCell::new(TableState::default());
         list_state.borrow_mut().select(Some(0));
 
@@ -60,10 +137,13 @@ impl FileExplorer {
 
         let mut modal = Modal::new(Box::new(InfoVariant::new(String::new())));
         modal.close();
-        Ok(Self {
+        let mut explorer = Self {
+            history: vec![current_dir.clone()],
+            history_index: 0,
+            file_clipboard: None,
+            background: BackgroundTasks::default(),
             current_dir,
-            selected_index: 0,
-            entries,
+            entries: Vec::new(),
             table_state: list_state,
             is_focused: false,
             interactive,
@@ -71,60 +151,249 @@ impl FileExplorer {
             modal,
             sender,
             receiver,
-            current_sort: 0,
+            current_sort: config.sort_index(),
+            directory_sort,
+            sort_reversed: false,
+            dirs_first: config.dirs_first,
+            relative_timestamps: config.relative_timestamps,
+            notification: None,
+            external_process: None,
+            scroll_offset: 0,
+            visible_rows: Cell::new(0),
+            compact: false,
+            ancestor_levels: None,
+            grid_rows: Cell::new(1),
+            grid_first_column: Cell::new(0),
             name,
-        })
+            global_config: config.clone(),
+            config: directory_config,
+        };
+        explorer.refresh()?;
+        Ok(explorer)
+    }
+
+    pub fn sort_name(&self) -> &'static str {
+        SORT_ENTRIES[self.sort_index()].name
+    }
+
+    fn sort_index(&self) -> usize {
+        self.directory_sort.unwrap_or(self.current_sort)
+    }
+
+    pub fn name_filter(&self) -> &str {
+        &self.name_filter
+    }
+
+    pub fn toggle_sort_direction(&mut self, _: KeyCode) -> bool {
+        self.sort_reversed = !self.sort_reversed;
+        let _ = self.refresh();
+        true
+    }
+
+    pub fn toggle_dirs_first(&mut self, _: KeyCode) -> bool {
+        self.dirs_first = !self.dirs_first;
+        let _ = self.refresh();
+        true
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.config.show_hidden
+    }
+
+    pub fn toggle_hidden(&mut self, _: KeyCode) -> bool {
+        let show_hidden = !self.config.show_hidden;
+        self.global_config.show_hidden = show_hidden;
+        self.config.show_hidden = show_hidden;
+        self.notification = Some(format!(
+            "Hidden files {}",
+            if show_hidden { "shown" } else { "hidden" }
+        ));
+        let _ = self.refresh();
+        true
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.table_state.borrow().selected().unwrap_or(0)
+    }
+
+    fn select(&mut self, index: usize) {
+        self.table_state.borrow_mut().select(Some(index));
     }
 
     pub fn select_previous(&mut self, _: KeyCode) -> bool {
-        if !self.entries.is_empty() && self.selected_index > 0 {
-            self.selected_index -= 1;
-            self.table_state
-                .borrow_mut()
-                .select(Some(self.selected_index));
+        let selected_index = self.selected_index();
+        if !self.entries.is_empty() && selected_index > 0 {
+            self.select(selected_index - 1);
         }
         true
     }
 
     pub fn select_next(&mut self, _: KeyCode) -> bool {
-        if !self.entries.is_empty() && self.selected_index < self.entries.len() - 1 {
-            self.selected_index += 1;
-            self.table_state
-                .borrow_mut()
-                .select(Some(self.selected_index));
+        let selected_index = self.selected_index();
+        if !self.entries.is_empty() && selected_index < self.entries.len() - 1 {
+            self.select(selected_index + 1);
         }
         true
     }
 
-    pub fn prompt_for_delete_current_file(&mut self, _: KeyCode) -> bool {
-        if let Some(selected_file) = self.get_selected_file() {
-            let sender = self.sender.clone();
-            self.modal = Modal::new(Box::new(ConfirmationVariant::new(
-                format!("Delete file: {}?", selected_file.to_str().unwrap()),
-                Box::new(move |_| {
+    fn select_column(&mut self, delta: isize) -> bool {
+        if !self.compact || self.entries.is_empty() {
+            return false;
+        }
+        let rows = self.grid_rows.get().max(1) as isize;
+        let target = self.selected_index() as isize + delta * rows;
+        if (0..self.entries.len() as isize).contains(&target) {
+            self.select(target as usize);
+        }
+        true
+    }
+
+    pub fn select_left(&mut self, _: KeyCode) -> bool {
+        self.select_column(-1)
+    }
+
+    pub fn select_right(&mut self, _: KeyCode) -> bool {
+        self.select_column(1)
+    }
+
+    pub fn show_item_count(&mut self, _: KeyCode) -> bool {
+        let (files, dirs) = self.entries.iter().fold((0, 0), |(files, dirs), entry| {
+            if entry.is_dir() {
+                (files, dirs + 1)
+            } else {
+                (files + 1, dirs)
+            }
+        });
+        let dir = self.current_dir.clone();
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(OptionsVariant::new(
+            format!(
+                "{} items ({} files, {} directories)",
+                files + dirs,
+                files,
+                dirs
+            ),
+            vec!["Close".to_string(), "Count recursively".to_string()],
+            Box::new(move |option| {
+                if option == 1 {
                     sender
-                        .send(ExplorerTask::DeleteFile(selected_file.clone()))
+                        .send(ExplorerTask::CountRecursive(dir.clone()))
                         .unwrap();
-                }),
-            )));
+                }
+            }),
+        )));
+        true
+    }
+
+    pub fn poll_tasks(&mut self) {
+        while let Ok(task) = self.receiver.try_recv() {
+            let _ = self.apply_task(task);
+        }
+    }
+
+    pub fn toggle_view(&mut self, _: KeyCode) -> bool {
+        self.compact = !self.compact;
+        self.grid_first_column.set(0);
+        true
+    }
+
+    pub fn prompt_for_delete_current_file(&mut self, _: KeyCode) -> bool {
+        if let Some(selected_file) = self.get_selected_file() {
+            if self.config.protect_nonempty_dirs && is_nonempty_dir(&selected_file) {
+                self.prompt_for_typed_delete(selected_file);
+                return true;
+            }
+            let message = format!("Delete file: {}?", selected_file.display());
+            let task = ExplorerTask::DeleteFile(selected_file);
+            if self.config.confirm_delete {
+                self.confirm(message, task);
+            } else {
+                let _ = self.apply_task(task);
+            }
         } else {
             self.open_info_modal("Selected file is invalid".to_string());
         }
         true
     }
 
+    fn prompt_for_typed_delete(&mut self, dir: PathBuf) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            format!(
+                "{} is not empty. Type its name to delete it:",
+                dir.display()
+            ),
+            String::new(),
+            Box::new(move |answer| {
+                sender
+                    .send(ExplorerTask::TypedDelete(dir.clone(), answer))
+                    .unwrap();
+            }),
+        )));
+    }
+
     pub fn prompt_for_move_file(&mut self, _: KeyCode) -> bool {
         if let Some(selected_file) = self.get_selected_file() {
             let sender = self.sender.clone();
-            self.modal = Modal::new(Box::new(QuestionVariant::new(
-                format!("Move file: {} to?", selected_file.to_str().unwrap()),
-                String::from(selected_file.to_str().unwrap()),
+            let (start, end) = stem_range(&selected_file);
+            self.modal = Modal::new(Box::new(
+                QuestionVariant::new(
+                    format!("Move file: {} to?", selected_file.display()),
+                    selected_file.display().to_string(),
+                    Box::new(move |answer| {
+                        sender
+                            .send(ExplorerTask::MoveFile(selected_file.clone(), answer))
+                            .unwrap();
+                    }),
+                )
+                .with_selection(start, end),
+            ));
+        } else {
+            self.open_info_modal("Selected file is invalid".to_string());
+        }
+        true
+    }
+
+    pub fn prompt_for_rename(&mut self, _: KeyCode) -> bool {
+        let Some(selected_file) = self.get_selected_file() else {
+            self.open_info_modal("Selected file is invalid".to_string());
+            return true;
+        };
+        let name = display_name(&selected_file);
+        // Only the name is prefilled, so the stem starts at its beginning.
+        let (start, end) = stem_range(&selected_file);
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(
+            QuestionVariant::new(
+                format!("Rename {} to:", name),
+                name,
                 Box::new(move |answer| {
                     sender
-                        .send(ExplorerTask::MoveFile(selected_file.clone(), answer))
+                        .send(ExplorerTask::RenameFile(selected_file.clone(), answer))
                         .unwrap();
                 }),
-            )));
+            )
+            .with_selection(0, end - start),
+        ));
+        true
+    }
+
+    pub fn prompt_for_copy_file(&mut self, _: KeyCode) -> bool {
+        if let Some(selected_file) = self.get_selected_file() {
+            let sender = self.sender.clone();
+            let (start, end) = stem_range(&selected_file);
+            self.modal = Modal::new(Box::new(
+                QuestionVariant::new(
+                    format!("Copy file: {} to?", selected_file.display()),
+                    selected_file.display().to_string(),
+                    Box::new(move |answer| {
+                        sender
+                            .send(ExplorerTask::CopyFile(selected_file.clone(), answer))
+                            .unwrap();
+                    }),
+                )
+                .with_selection(start, end),
+            ));
         } else {
             self.open_info_modal("Selected file is invalid".to_string());
         }
@@ -160,242 +429,1445 @@ impl FileExplorer {
         true
     }
 
-    pub fn prompt_for_new_filter(&mut self, _: KeyCode) -> bool {
+    pub fn prompt_for_new_dir_and_enter(&mut self, _: KeyCode) -> bool {
         let sender = self.sender.clone();
         self.modal = Modal::new(Box::new(QuestionVariant::new(
-            String::from("Filter: "),
+            String::from("Create directory and enter it:"),
             String::new(),
             Box::new(move |answer| {
-                sender.send(ExplorerTask::Filter(answer)).unwrap();
+                sender.send(ExplorerTask::CreateAndEnter(answer)).unwrap();
             }),
         )));
 
         true
     }
 
-    pub fn go_back(&mut self, _: KeyCode) -> bool {
-        if let Some(parent) = self.current_dir.parent() {
-            let _ = self.set_path(parent.to_path_buf());
+    pub fn prompt_for_template(&mut self, _: KeyCode) -> bool {
+        let templates_dir = paths::templates_dir();
+        let templates: Vec<PathBuf> = read_dir_entries(&templates_dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|path| path.is_file())
+            .take(9)
+            .collect();
+        if templates.is_empty() {
+            self.open_info_modal(format!("No templates in {}", templates_dir.display()));
+            return true;
         }
+
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(OptionsVariant::new(
+            String::from("New file from template:"),
+            templates.iter().map(|path| display_name(path)).collect(),
+            Box::new(move |option| {
+                sender
+                    .send(ExplorerTask::PickTemplate(templates[option].clone()))
+                    .unwrap();
+            }),
+        )));
         true
     }
 
-    fn open_info_modal(&mut self, message: String) {
-        self.modal = Modal::new(Box::new(InfoVariant::new(message)));
+    pub fn prompt_for_new_filter(&mut self, _: KeyCode) -> bool {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            String::from("Filter: "),
+            String::new(),
+            Box::new(move |answer| {
+                sender.send(ExplorerTask::Filter(answer)).unwrap();
+            }),
+        )));
+
+        true
     }
 
-    pub fn get_selected_file(&self) -> Option<PathBuf> {
-        self.entries.get(self.selected_index).cloned()
+    pub fn toggle_timestamp_format(&mut self, _: KeyCode) -> bool {
+        self.relative_timestamps = !self.relative_timestamps;
+        true
     }
 
-    pub fn open_selected_file(&mut self, _: KeyCode) -> bool {
-        if let Some(selected_file) = self.get_selected_file() {
-            if selected_file.is_dir() {
-                let _ = self.set_path(selected_file);
-                return true;
+    fn copy_to_clipboard(&mut self, text: String) {
+        self.notification = Some(match clipboard::copy(&text) {
+            Ok(()) => format!("Copied {}", text),
+            Err(e) => {
+                log::warn!("could not copy to clipboard: {}", e);
+                format!("Clipboard unavailable: {}", text)
             }
-        }
-        false
+        });
     }
 
-    fn refresh(&mut self) -> Result<()> {
-        self.entries = read_dir_entries(&self.current_dir)?
-            .iter()
-            .map(|entry| entry.clone())
-            .filter(|entry| {
-                let name = entry.file_name().unwrap().to_str().unwrap();
-                name.to_lowercase()
-                    .contains(&self.name_filter.to_lowercase())
-            })
-            .collect();
+    pub fn yank_file(&mut self, _: KeyCode) -> bool {
+        if let Some(file) = self.get_selected_file() {
+            self.notification = Some(format!("Yanked {}", display_name(&file)));
+            self.file_clipboard = Some((file, ClipboardOp::Copy));
+        }
+        true
+    }
 
-        (SORT_ENTRIES[self.current_sort].func)(&mut self.entries)?;
-        self.table_state.borrow_mut().select(Some(0));
-        self.selected_index = 0;
-        Ok(())
+    pub fn cut_file(&mut self, _: KeyCode) -> bool {
+        if let Some(file) = self.get_selected_file() {
+            self.notification = Some(format!("Cut {}", display_name(&file)));
+            self.file_clipboard = Some((file, ClipboardOp::Cut));
+        }
+        true
     }
 
-    fn dispatch_on_task(&mut self, task: ExplorerTask) -> Result<()> {
-        Ok(match task {
-            ExplorerTask::CreateFile(name) => {
-                let new_file = self.current_dir.join(&name);
-                if new_file.try_exists().unwrap_or(false) {
-                    self.open_info_modal("File already exists".to_string());
-                } else {
-                    let create = || -> Result<()> {
-                        if name.ends_with("/") {
-                            Ok(fs::create_dir(new_file)?)
-                        } else {
-                            fs::File::create(&new_file)?;
-                            Ok(())
-                        }
-                    };
-                    match create() {
-                        Ok(_) => {}
-                        Err(_) => self.open_info_modal("Could not create the file".to_string()),
-                    }
-                }
-                self.refresh()?;
+    pub fn paste_file(&mut self, _: KeyCode) -> bool {
+        match self.file_clipboard.clone() {
+            Some((source, op)) => {
+                let _ = self.apply_task(ExplorerTask::Paste(source, op));
             }
-            ExplorerTask::DeleteFile(filepath) => {
-                let removal = || {
-                    if filepath.is_dir() {
-                        fs::remove_dir_all(filepath)
-                    } else {
-                        fs::remove_file(filepath)
-                    }
-                };
+            None => self.notification = Some("Nothing to paste".to_string()),
+        }
+        true
+    }
 
-                if let Err(e) = removal() {
-                    self.open_info_modal(format!("Could not delete: {}", e));
-                } else {
-                    self.refresh()?;
-                }
-            }
-            ExplorerTask::MoveFile(original, new_path) => {
-                let newpath = PathBuf::from(new_path);
-                if let Err(e) = fs::rename(original, &newpath) {
-                    self.open_info_modal(format!("Could not move file: {}", e));
-                } else {
-                    self.refresh()?;
-                }
-            }
-            ExplorerTask::Sort(entry_index) => {
-                self.current_sort = entry_index;
-                self.refresh()?;
-            }
-            ExplorerTask::Filter(search) => {
-                self.name_filter = search;
-                self.refresh()?;
-            }
-        })
+    pub fn copy_path(&mut self, _: KeyCode) -> bool {
+        if let Some(file) = self.get_selected_file() {
+            let path = std::path::absolute(&file).unwrap_or(file);
+            self.copy_to_clipboard(path.display().to_string());
+        }
+        true
     }
-}
 
-impl Drawable for FileExplorer {
-    fn draw(&self, f: &mut Frame, area: Rect) {
-        if self.modal.is_open() {
-            self.modal.draw(f, area);
-            return;
+    pub fn copy_name(&mut self, _: KeyCode) -> bool {
+        if let Some(file) = self.get_selected_file() {
+            self.copy_to_clipboard(display_name(&file));
         }
+        true
+    }
 
-        let file_rows: Vec<Row> = self
+    pub fn copy_relative_path(&mut self, _: KeyCode) -> bool {
+        let Some(file) = self.get_selected_file() else {
+            return true;
+        };
+        let relative = self.relative_path(&file);
+        self.copy_to_clipboard(relative.display().to_string());
+        true
+    }
+
+    fn relative_path(&self, file: &Path) -> PathBuf {
+        let root = self
+            .global_config
+            .relative_root
+            .clone()
+            .or_else(|| std::env::current_dir().ok());
+        let path = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        root.and_then(|root| std::path::absolute(root).ok())
+            .and_then(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .unwrap_or(path)
+    }
+
+    fn scroll_by(&mut self, delta: isize) -> bool {
+        let max_offset = self
             .entries
-            .iter()
-            .map(|entry| {
-                let name = entry.file_name().unwrap().to_str().unwrap();
-                let file_type = if entry.is_dir() { "dir" } else { "file" };
-                if let Ok(file_metadata) = entry.metadata() {
-                    let file_size = file_metadata.len();
-                    let readable_size =
-                        Byte::from_u64(file_size).get_appropriate_unit(byte_unit::UnitType::Binary);
+            .len()
+            .saturating_sub(self.visible_rows.get().max(1));
+        self.scroll_offset = self
+            .scroll_offset
+            .saturating_add_signed(delta)
+            .min(max_offset);
+        true
+    }
 
-                    Row::new([
-                        Span::from(file_type).style(Style::default().fg(Color::Green)),
-                        Span::from(format!("{readable_size:.2}")),
-                        Span::from(name),
-                    ])
-                } else {
-                    Row::new([
-                        Span::from(file_type).style(Style::default().fg(Color::Green)),
-                        Span::from("?"),
-                        Span::from(name),
-                    ])
-                }
-            })
-            .collect();
+    pub fn scroll_down(&mut self, _: KeyCode) -> bool {
+        self.scroll_by(1)
+    }
 
-        let mut block = Block::default()
-            .borders(Borders::ALL)
-            .title(self.current_dir.to_str().unwrap());
+    pub fn scroll_up(&mut self, _: KeyCode) -> bool {
+        self.scroll_by(-1)
+    }
 
-        if self.is_focused {
-            block = block.border_style(Color::Blue);
-        }
+    pub fn page_down(&mut self, _: KeyCode) -> bool {
+        self.scroll_by(self.visible_rows.get().max(1) as isize)
+    }
 
-        let mut table_state = self.table_state.borrow_mut();
-        let widths = [
-            Constraint::Percentage(10),
-            Constraint::Percentage(20),
-            Constraint::Percentage(70),
-        ];
-        let mut table = Table::new(file_rows, widths)
-            .block(block)
-            .header(Row::new(vec!["Type", "Size", "Name"]));
+    pub fn page_up(&mut self, _: KeyCode) -> bool {
+        self.scroll_by(-(self.visible_rows.get().max(1) as isize))
+    }
 
-        if self.is_focused {
-            table = table
-                .highlight_symbol(">>")
-                .highlight_style(Style::default().bg(Color::Blue));
+    pub fn go_back(&mut self, _: KeyCode) -> bool {
+        if self.ancestor_levels.take().is_some() {
+            return true;
+        }
+        if !self.name_filter.is_empty() {
+            let _ = self.apply_task(ExplorerTask::Filter(String::new()));
+            return true;
+        }
+        if let Some(parent) = self.current_dir.parent() {
+            let _ = self.set_path(parent.to_path_buf());
         }
+        true
+    }
 
-        f.render_stateful_widget(table, area, &mut table_state);
+    pub fn cancel_background(&mut self, _: KeyCode) -> bool {
+        let tasks = self.background_tasks();
+        if tasks.is_empty() {
+            self.notification = Some("Nothing is running in the background".to_string());
+        } else {
+            let message = format!("Cancel these operations?\n{}", tasks.join("\n"));
+            self.confirm(message, ExplorerTask::CancelBackground);
+        }
+        true
     }
-}
 
-impl Focusable for FileExplorer {
-    fn focus(&mut self) {
-        if self.interactive {
-            self.is_focused = true;
+    pub fn history_back(&mut self, _: KeyCode) -> bool {
+        if self.history_index > 0 {
+            self.goto_history(self.history_index - 1);
         }
+        true
     }
 
-    fn unfocus(&mut self) {
-        self.is_focused = false;
+    pub fn history_forward(&mut self, _: KeyCode) -> bool {
+        if self.history_index + 1 < self.history.len() {
+            self.goto_history(self.history_index + 1);
+        }
+        true
     }
 
-    fn is_focused(&self) -> bool {
-        self.is_focused && self.interactive
+    fn goto_history(&mut self, index: usize) {
+        let dir = self.history[index].clone();
+        match self.load_dir(dir) {
+            Ok(_) => self.history_index = index,
+            Err(e) => self.open_info_modal(format!("Could not open directory: {}", e)),
+        }
     }
-}
 
-impl InputHandler for FileExplorer {
-    fn handle_input(&mut self, key_code: KeyCode) -> bool {
-        if self.modal.is_open() {
-            self.modal.handle_input(key_code);
-            if let Ok(task) = self.receiver.try_recv() {
-                let _ = self.dispatch_on_task(task);
-            }
-            true
-        } else {
-            self.handle_command(key_code)
+    fn record_history(&mut self) {
+        if self.history.get(self.history_index) == Some(&self.current_dir) {
+            return;
         }
+        self.history.truncate(self.history_index + 1);
+        self.history.push(self.current_dir.clone());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        self.history_index = self.history.len() - 1;
     }
-}
 
-impl Editor for FileExplorer {
-    fn set_path(&mut self, new_dir: PathBuf) -> Result<()> {
-        self.entries = read_dir_entries(&new_dir)?;
+    fn load_dir(&mut self, new_dir: PathBuf) -> Result<()> {
+        read_dir_entries(&new_dir)?;
+        self.config = self.global_config.for_directory(&new_dir);
         self.current_dir = new_dir;
-        self.selected_index = 0;
+        self.ancestor_levels = None;
         self.name_filter = String::new();
-        self.current_sort = 0;
-        self.table_state
-            .borrow_mut()
-            .select(Some(self.selected_index));
-        Ok(())
+        self.directory_sort = Config::directory_sort(&self.current_dir);
+        self.scroll_offset = 0;
+        self.refresh()
     }
-}
 
-fn read_dir_entries(dir: &PathBuf) -> Result<Vec<PathBuf>> {
-    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
-        .context("Could not read directory entries")?
-        .filter(|res| res.is_ok())
-        .map(|res| res.unwrap().path())
-        .collect();
-    entries.sort();
-    Ok(entries)
-}
-
-impl CommandHandler for FileExplorer {
-    fn get_name(&self) -> &'static str {
-        self.name
+    pub fn goto_target(&mut self, _: KeyCode) -> bool {
+        let Some(link) = self.get_selected_file() else {
+            return true;
+        };
+        if !link.is_symlink() {
+            self.notification = Some(format!("{} is not a symlink", display_name(&link)));
+            return true;
+        }
+        let Ok(target) = fs::read_link(&link) else {
+            self.open_info_modal(format!("Could not read link {}", display_name(&link)));
+            return true;
+        };
+        let Ok(resolved) = link
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(&target)
+            .canonicalize()
+        else {
+            self.open_info_modal(format!(
+                "Broken link: {} -> {}",
+                display_name(&link),
+                target.display()
+            ));
+            return true;
+        };
+        if let Err(e) = self.reveal(&resolved) {
+            self.open_info_modal(e.to_string());
+        }
+        true
     }
-    fn get_commands(&self) -> Vec<Command<Self>> {
+
+    pub fn reveal(&mut self, path: &Path) -> Result<()> {
+        let Some(parent) = path.parent() else {
+            return self.set_path(path.to_path_buf());
+        };
+        self.set_path(parent.to_path_buf())
+            .with_context(|| format!("Could not open {}", parent.display()))?;
+        self.select_path(path);
+        Ok(())
+    }
+
+    fn select_path(&mut self, path: &Path) {
+        if let Some(index) = self.entries.iter().position(|entry| entry == path) {
+            self.select(index);
+        }
+    }
+
+    fn confirm(&mut self, message: String, task: ExplorerTask) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(ConfirmationVariant::new(
+            message,
+            Box::new(move |_| {
+                sender.send(task.clone()).unwrap();
+            }),
+        )));
+    }
+
+    fn open_info_modal(&mut self, message: String) {
+        self.modal = Modal::new(Box::new(InfoVariant::new(message)));
+    }
+
+    pub fn background_tasks(&self) -> Vec<&str> {
+        self.background.descriptions().collect()
+    }
+
+    pub fn take_notification(&mut self) -> Option<String> {
+        self.notification.take()
+    }
+
+    pub fn take_external_process(&mut self) -> Option<ProcessCommand> {
+        self.external_process.take()
+    }
+
+    pub fn prompt_for_command(&mut self, _: KeyCode) -> bool {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            format!(
+                "Run in {} (prefix with ! for interactive commands):",
+                self.current_dir.display()
+            ),
+            String::new(),
+            Box::new(move |answer| {
+                sender.send(ExplorerTask::RunCommand(answer)).unwrap();
+            }),
+        )));
+        true
+    }
+
+    pub fn get_selected_file(&self) -> Option<PathBuf> {
+        self.entries.get(self.selected_index()).cloned()
+    }
+
+    fn goto_sibling_dir(&mut self, step: isize) -> bool {
+        let Some(parent) = self.current_dir.parent() else {
+            self.open_info_modal("The root directory has no siblings".to_string());
+            return true;
+        };
+        let siblings: Vec<PathBuf> = read_dir_entries(&parent.to_path_buf())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|entry| entry.is_dir())
+            .filter(|entry| {
+                self.global_config.show_hidden
+                    || !display_name(entry).starts_with('.')
+                    || *entry == self.current_dir
+            })
+            .collect();
+        let Some(position) = siblings.iter().position(|dir| *dir == self.current_dir) else {
+            return true;
+        };
+        if siblings.len() == 1 {
+            self.open_info_modal(format!(
+                "{} has no sibling directories",
+                display_name(&self.current_dir)
+            ));
+            return true;
+        }
+
+        let target = position as isize + step;
+        let target = if self.global_config.wrap_sibling_dirs {
+            target.rem_euclid(siblings.len() as isize) as usize
+        } else if (0..siblings.len() as isize).contains(&target) {
+            target as usize
+        } else {
+            self.notification = Some("No more sibling directories".to_string());
+            return true;
+        };
+        if let Err(e) = self.set_path(siblings[target].clone()) {
+            self.open_info_modal(e.to_string());
+        }
+        true
+    }
+
+    pub fn next_sibling_dir(&mut self, _: KeyCode) -> bool {
+        self.goto_sibling_dir(1)
+    }
+
+    pub fn prev_sibling_dir(&mut self, _: KeyCode) -> bool {
+        self.goto_sibling_dir(-1)
+    }
+
+    pub fn select_ancestor(&mut self, _: KeyCode) -> bool {
+        let depth = self.current_dir.ancestors().count() - 1;
+        self.ancestor_levels = match self.ancestor_levels {
+            Some(levels) if levels < depth => Some(levels + 1),
+            Some(_) => None,
+            None if depth > 0 => Some(1),
+            None => None,
+        };
+        true
+    }
+
+    pub fn open_selected_file(&mut self, _: KeyCode) -> bool {
+        if let Some(levels) = self.ancestor_levels {
+            if let Some(ancestor) = self.current_dir.ancestors().nth(levels) {
+                let _ = self.set_path(ancestor.to_path_buf());
+            }
+            return true;
+        }
+        if let Some(selected_file) = self.get_selected_file() {
+            let action = open_action_for(&selected_file, &self.global_config.open);
+            if action == OpenAction::Navigate && selected_file.is_dir() {
+                let _ = self.set_path(selected_file);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn refresh(&mut self) -> Result<()> {
+        if !self.current_dir.is_dir() {
+            return self.leave_missing_dir();
+        }
+
+        self.entries = read_dir_entries(&self.current_dir)?
+            .into_iter()
+            .filter(|entry| {
+                let name = display_name(entry);
+                if !self.config.show_hidden && name.starts_with('.') {
+                    return false;
+                }
+                let name = match self.config.filter_scope {
+                    FilterScope::Name => name,
+                    FilterScope::Path => self.relative_path(entry).display().to_string(),
+                };
+                if self.config.case_sensitive_filter {
+                    name.contains(self.name_filter.as_str())
+                } else {
+                    name.to_lowercase()
+                        .contains(&self.name_filter.to_lowercase())
+                }
+            })
+            .collect();
+
+        (SORT_ENTRIES[self.sort_index()].func)(&mut self.entries)?;
+        if self.sort_reversed {
+            self.entries.reverse();
+        }
+        if self.dirs_first {
+            // is_dir stats the entry, so it is only called once per entry.
+            // The sort is stable and keeps the chosen order within each group.
+            self.entries.sort_by_cached_key(|entry| !entry.is_dir());
+        }
+        self.select(0);
+        Ok(())
+    }
+
+    fn leave_missing_dir(&mut self) -> Result<()> {
+        let missing = self.current_dir.clone();
+        let ancestor = missing
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.is_dir())
+            .context("No existing parent directory")?;
+        self.set_path(ancestor.to_path_buf())?;
+        self.notification = Some(format!("{} no longer exists", missing.display()));
+        Ok(())
+    }
+
+    fn delete_dir_in_background(&mut self, dir: PathBuf) {
+        let cancelled = self.background.start(deleting(&dir));
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let mut removed = 0;
+            let result = remove_tree(&dir, &cancelled, &mut || {
+                removed += 1;
+                if removed % DELETE_PROGRESS_STEP == 0 {
+                    let _ = sender.send(ExplorerTask::DeleteProgress(dir.clone(), removed));
+                }
+            });
+            let task = match result {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+                    ExplorerTask::DeleteCancelled(dir, removed)
+                }
+                result => {
+                    let result = result.map(|_| removed).map_err(|e| e.to_string());
+                    ExplorerTask::DeleteFinished(dir, result)
+                }
+            };
+            let _ = sender.send(task);
+        });
+    }
+
+    fn create_file(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let new_file = self.current_dir.join(name);
+        if let Err(e) = validate_name(name.strip_suffix('/').unwrap_or(name)) {
+            self.open_info_modal(e.to_string());
+        } else if new_file.try_exists().unwrap_or(false) {
+            self.open_info_modal("File already exists".to_string());
+        } else {
+            let create = || -> Result<()> {
+                if name.ends_with('/') {
+                    Ok(fs::create_dir(&new_file)?)
+                } else {
+                    Ok(fs::write(&new_file, contents)?)
+                }
+            };
+            match create() {
+                Ok(_) => {
+                    self.notification = Some(format!("Created {}", name));
+                    self.refresh()?;
+                    self.select_path(&new_file);
+                    return Ok(());
+                }
+                Err(_) => self.open_info_modal("Could not create the file".to_string()),
+            }
+        }
+        self.refresh()
+    }
+
+    fn create_dir_and_enter(&mut self, name: &str) -> Result<()> {
+        let parts: Vec<&str> = name
+            .split(std::path::is_separator)
+            .filter(|part| !part.is_empty())
+            .collect();
+        let valid = if parts.is_empty() {
+            validate_name(name)
+        } else {
+            parts.iter().try_for_each(|part| validate_name(part))
+        };
+        if let Err(e) = valid {
+            self.open_info_modal(e.to_string());
+            return Ok(());
+        }
+        let new_dir = parts
+            .iter()
+            .fold(self.current_dir.clone(), |dir, part| dir.join(part));
+        if let Err(e) = fs::create_dir_all(&new_dir) {
+            self.open_info_modal(format!("Could not create directory: {}", e));
+            return Ok(());
+        }
+        if let Err(e) = self.set_path(new_dir) {
+            self.open_info_modal(format!("Could not open directory: {}", e));
+            return self.refresh();
+        }
+        self.notification = Some(format!("Created {}", name));
+        Ok(())
+    }
+
+    fn copy_file(&mut self, original: &Path, new_path: &str) -> Result<()> {
+        let newpath = destination(original, new_path);
+        let new_name = newpath.file_name().map(|name| name.to_string_lossy());
+        if let Err(e) = validate_name(new_name.as_deref().unwrap_or("")) {
+            self.open_info_modal(e.to_string());
+        } else if newpath.symlink_metadata().is_ok() {
+            self.open_info_modal(format!("{} already exists", newpath.display()));
+        } else if is_same_or_inside(original, &newpath) {
+            self.open_info_modal(format!(
+                "Cannot copy {} into itself",
+                display_name(original)
+            ));
+        } else if let Err(e) = copy_tree(original, &newpath) {
+            self.open_info_modal(format!("Could not copy: {}", e));
+            self.refresh()?;
+        } else {
+            self.notification = Some(format!(
+                "Copied {} to {}",
+                display_name(original),
+                newpath.display()
+            ));
+            self.refresh()?;
+            self.select_path(&newpath);
+        }
+        Ok(())
+    }
+
+    fn paste(&mut self, source: &Path, op: ClipboardOp) -> Result<()> {
+        let Some(name) = source.file_name() else {
+            return Ok(());
+        };
+        let destination = self.current_dir.join(name);
+        if destination.symlink_metadata().is_ok() {
+            self.open_info_modal(format!("{} already exists", destination.display()));
+            return Ok(());
+        }
+        if is_same_or_inside(source, &destination) {
+            self.open_info_modal(format!("Cannot paste {} into itself", display_name(source)));
+            return Ok(());
+        }
+        let result = match op {
+            ClipboardOp::Copy => copy_tree(source, &destination),
+            ClipboardOp::Cut => move_tree(source, &destination),
+        };
+        match result {
+            Ok(_) => {
+                if op == ClipboardOp::Cut {
+                    self.file_clipboard = None;
+                }
+                self.notification = Some(format!("Pasted {}", display_name(&destination)));
+                self.refresh()?;
+                self.select_path(&destination);
+            }
+            Err(e) => {
+                self.open_info_modal(format!("Could not paste: {}", e));
+                self.refresh()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn move_file(&mut self, original: &Path, newpath: &Path) -> Result<()> {
+        if let Err(e) = move_tree(original, newpath) {
+            self.open_info_modal(format!("Could not move file: {}", e));
+            return Ok(());
+        }
+        self.notification = Some(format!(
+            "Moved {} to {}",
+            display_name(original),
+            newpath.display()
+        ));
+        self.refresh()?;
+        self.select_path(newpath);
+        Ok(())
+    }
+
+    pub fn apply_task(&mut self, task: ExplorerTask) -> Result<()> {
+        if self.global_config.read_only && task.is_mutating() {
+            self.open_info_modal("Read-only mode: the file system cannot be changed".to_string());
+            return Ok(());
+        }
+        match task {
+            ExplorerTask::CreateFile(name) => self.create_file(&name, &[])?,
+            ExplorerTask::CreateAndEnter(name) => self.create_dir_and_enter(&name)?,
+            ExplorerTask::PickTemplate(template) => {
+                let sender = self.sender.clone();
+                self.modal = Modal::new(Box::new(QuestionVariant::new(
+                    format!("Create file from {}:", display_name(&template)),
+                    display_name(&template),
+                    Box::new(move |answer| {
+                        sender
+                            .send(ExplorerTask::CreateFromTemplate(template.clone(), answer))
+                            .unwrap();
+                    }),
+                )));
+            }
+            ExplorerTask::CreateFromTemplate(template, name) => match fs::read(&template) {
+                Ok(contents) => self.create_file(&name, &contents)?,
+                Err(e) => self.open_info_modal(format!("Could not read template: {}", e)),
+            },
+            ExplorerTask::DeleteFile(filepath) => {
+                let is_dir = fs::symlink_metadata(&filepath).is_ok_and(|m| m.is_dir());
+                if is_dir {
+                    self.delete_dir_in_background(filepath);
+                } else if let Err(e) = fs::remove_file(&filepath) {
+                    self.open_info_modal(format!("Could not delete: {}", e));
+                } else {
+                    self.notification = Some(format!("Deleted {}", display_name(&filepath)));
+                    self.refresh()?;
+                }
+            }
+            ExplorerTask::TypedDelete(dir, answer) => {
+                if answer == display_name(&dir) {
+                    self.apply_task(ExplorerTask::DeleteFile(dir))?;
+                } else {
+                    self.open_info_modal(format!(
+                        "The name did not match, {} was not deleted",
+                        display_name(&dir)
+                    ));
+                }
+            }
+            ExplorerTask::DeleteProgress(dir, removed) => {
+                self.notification = Some(format!(
+                    "Deleting {}: {} entries removed",
+                    display_name(&dir),
+                    removed
+                ));
+            }
+            ExplorerTask::DeleteFinished(dir, result) => {
+                self.background.finish(&deleting(&dir));
+                match result {
+                    Ok(removed) => {
+                        self.notification = Some(format!(
+                            "Deleted {} ({} entries)",
+                            display_name(&dir),
+                            removed
+                        ))
+                    }
+                    Err(e) => self.open_info_modal(format!("Could not delete: {}", e)),
+                }
+                self.refresh()?;
+            }
+            ExplorerTask::DeleteCancelled(dir, removed) => {
+                self.background.finish(&deleting(&dir));
+                self.notification = Some(format!(
+                    "Stopped deleting {} after {} entries",
+                    display_name(&dir),
+                    removed
+                ));
+                self.refresh()?;
+            }
+            ExplorerTask::MoveFile(original, new_path) => {
+                let newpath = destination(&original, &new_path);
+                let new_name = newpath.file_name().map(|name| name.to_string_lossy());
+                if let Err(e) = validate_name(new_name.as_deref().unwrap_or("")) {
+                    self.open_info_modal(e.to_string());
+                } else if is_same_location(&original, &newpath) {
+                    return Ok(());
+                } else if is_same_or_inside(&original, &newpath) {
+                    self.open_info_modal(format!(
+                        "Cannot move {} into itself",
+                        display_name(&original)
+                    ));
+                } else if self.config.confirm_overwrite && newpath.exists() {
+                    self.confirm(
+                        format!("Overwrite {}?", newpath.display()),
+                        ExplorerTask::ConfirmedMove(original, newpath),
+                    );
+                } else if self.config.confirm_move {
+                    self.confirm(
+                        format!("Move {} to {}?", display_name(&original), newpath.display()),
+                        ExplorerTask::ConfirmedMove(original, newpath),
+                    );
+                } else {
+                    self.move_file(&original, &newpath)?;
+                }
+            }
+            ExplorerTask::CountRecursive(dir) => {
+                let sender = self.sender.clone();
+                let show_hidden = self.config.show_hidden;
+                let cancelled = self.background.start(counting(&dir));
+                std::thread::spawn(move || {
+                    let counts = count_recursive(&dir, show_hidden, &cancelled);
+                    let complete = !is_cancelled(&cancelled);
+                    let _ = sender.send(ExplorerTask::CountFinished(dir, counts, complete));
+                });
+            }
+            ExplorerTask::CountFinished(dir, (files, dirs), complete) => {
+                self.background.finish(&counting(&dir));
+                self.open_info_modal(format!(
+                    "{} contains {}{} files and {} directories",
+                    dir.display(),
+                    if complete { "" } else { "at least " },
+                    files,
+                    dirs
+                ));
+            }
+            ExplorerTask::RunCommand(command) => {
+                if let Some(command) = command.strip_prefix('!') {
+                    self.external_process = Some(shell_command(command, &self.current_dir));
+                    return Ok(());
+                }
+                if command.trim().is_empty() {
+                    return Ok(());
+                }
+                let sender = self.sender.clone();
+                let mut process = shell_command(&command, &self.current_dir);
+                std::thread::spawn(move || {
+                    let message = match process.output() {
+                        Ok(output) => {
+                            let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+                            text.push_str(&String::from_utf8_lossy(&output.stderr));
+                            if !output.status.success() {
+                                text.push_str(&format!("\n{}", output.status));
+                            }
+                            if text.trim().is_empty() {
+                                text = format!("{} finished without output", command);
+                            }
+                            text.trim_end().to_string()
+                        }
+                        Err(e) => format!("Could not run {}: {}", command, e),
+                    };
+                    let _ = sender.send(ExplorerTask::ShowInfo(message));
+                });
+            }
+            ExplorerTask::CancelBackground => {
+                if self.background.cancel_all() {
+                    self.notification = Some("Cancelling background operations".to_string());
+                }
+            }
+            ExplorerTask::ShowInfo(message) => {
+                self.open_info_modal(message);
+                // The command that produced the message may have changed the listing.
+                self.refresh()?;
+            }
+            ExplorerTask::RenameFile(original, new_name) => {
+                let newpath = self.current_dir.join(&new_name);
+                if let Err(e) = validate_name(&new_name) {
+                    self.open_info_modal(e.to_string());
+                } else if newpath == original {
+                    return Ok(());
+                } else if self.config.confirm_overwrite && newpath.exists() {
+                    self.confirm(
+                        format!("Overwrite {}?", newpath.display()),
+                        ExplorerTask::ConfirmedMove(original, newpath),
+                    );
+                } else {
+                    self.move_file(&original, &newpath)?;
+                }
+            }
+            ExplorerTask::CopyFile(original, new_path) => self.copy_file(&original, &new_path)?,
+            ExplorerTask::Paste(source, op) => self.paste(&source, op)?,
+            ExplorerTask::ConfirmedMove(original, newpath) => {
+                self.move_file(&original, &newpath)?;
+            }
+            ExplorerTask::Sort(entry_index) => {
+                self.current_sort = entry_index;
+                self.directory_sort = None;
+                self.refresh()?;
+            }
+            ExplorerTask::Filter(search) => {
+                self.name_filter = search;
+                self.refresh()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FileExplorer {
+    fn block(&self) -> Block<'_> {
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(self.breadcrumb());
+        if self.interactive {
+            block = block.title(Title::from(self.view_status()).alignment(Alignment::Right));
+        }
+        if self.is_focused {
+            block.border_style(Color::Blue)
+        } else {
+            block
+        }
+    }
+
+    fn view_status(&self) -> String {
+        let sort = &SORT_ENTRIES[self.sort_index()];
+        let mut status = format!(
+            "sort:{}{} hidden:{}",
+            sort.name.to_lowercase(),
+            if sort.descending != self.sort_reversed {
+                "↓"
+            } else {
+                "↑"
+            },
+            if self.config.show_hidden { "on" } else { "off" }
+        );
+        if self.dirs_first {
+            status.push_str(" dirs-first");
+        }
+        if !self.name_filter.is_empty() {
+            status.push_str(&format!(" filter:{:?}", self.name_filter));
+        }
+        format!("[{}]", status)
+    }
+
+    fn breadcrumb(&self) -> Line<'static> {
+        let title = self.current_dir.display().to_string();
+        let Some(ancestor) = self
+            .ancestor_levels
+            .and_then(|levels| self.current_dir.ancestors().nth(levels))
+        else {
+            return Line::from(title);
+        };
+        let end = ancestor.as_os_str().len().min(title.len());
+        let start = ancestor
+            .file_name()
+            .map_or(0, |name| end.saturating_sub(name.len()));
+        if !title.is_char_boundary(start) || !title.is_char_boundary(end) {
+            return Line::from(title);
+        }
+        Line::from(vec![
+            Span::from(title[..start].to_string()),
+            Span::styled(
+                title[start..end].to_string(),
+                Style::default().bg(Color::Blue),
+            ),
+            Span::from(title[end..].to_string()),
+        ])
+    }
+
+    fn highlight_filter(&self, name: String, style: Style) -> Line<'static> {
+        let Some((start, end)) =
+            find_match(&name, &self.name_filter, self.config.case_sensitive_filter)
+        else {
+            return Line::from(Span::styled(name, style));
+        };
+        let highlight = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        Line::from(vec![
+            Span::styled(name[..start].to_string(), style),
+            Span::styled(name[start..end].to_string(), highlight),
+            Span::styled(name[end..].to_string(), style),
+        ])
+    }
+
+    fn draw_grid(&self, f: &mut Frame, area: Rect) {
+        let inner_width = area.width.saturating_sub(2) as usize;
+        let rows = (area.height.saturating_sub(2) as usize).max(1);
+        let names: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let name = display_name(entry);
+                if entry.is_dir() {
+                    format!("{name}/")
+                } else {
+                    name
+                }
+            })
+            .collect();
+        let longest = names.iter().map(|n| n.width()).max().unwrap_or(0);
+        let column_width = (longest + 2).clamp(1, inner_width.max(1));
+        let visible_columns = (inner_width / column_width).max(1);
+
+        let selected_column = self.selected_index() / rows;
+        let mut first_column = self.grid_first_column.get();
+        if selected_column < first_column {
+            first_column = selected_column;
+        } else if selected_column >= first_column + visible_columns {
+            first_column = selected_column + 1 - visible_columns;
+        }
+        self.grid_rows.set(rows);
+        self.grid_first_column.set(first_column);
+
+        let lines: Vec<Line> = (0..rows)
+            .map(|row| {
+                let spans: Vec<Vec<Span>> = (first_column..first_column + visible_columns)
+                    .map(|column| column * rows + row)
+                    .filter(|&index| index < names.len())
+                    .map(|index| {
+                        let name = truncate_name(&names[index], column_width - 1);
+                        let cell = pad_to_width(&name, column_width);
+                        let style = if self.is_focused && index == self.selected_index() {
+                            Style::default().bg(Color::Blue)
+                        } else if self.entries[index].is_dir() {
+                            Style::default().fg(Color::Green)
+                        } else {
+                            Style::default()
+                        };
+                        self.highlight_filter(cell, style).spans
+                    })
+                    .collect();
+                Line::from(spans.concat())
+            })
+            .collect();
+
+        f.render_widget(Paragraph::new(lines).block(self.block()), area);
+    }
+
+    fn draw_table(&self, f: &mut Frame, area: Rect) {
+        let widths = [
+            Constraint::Percentage(10),
+            Constraint::Percentage(20),
+            Constraint::Length(16),
+            Constraint::Fill(1),
+        ];
+        let highlight_symbol = if self.is_focused && self.interactive {
+            ">>"
+        } else {
+            ""
+        };
+        let name_width = name_column_width(area, &widths, highlight_symbol);
+
+        let file_rows: Vec<Row> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let name = truncate_name(entry.file_name().unwrap().to_str().unwrap(), name_width);
+                let file_type = if entry.is_dir() { "dir" } else { "file" };
+                if let Ok(file_metadata) = entry.metadata() {
+                    let file_size = file_metadata.len();
+                    let readable_size =
+                        Byte::from_u64(file_size).get_appropriate_unit(byte_unit::UnitType::Binary);
+                    let modified = file_metadata
+                        .modified()
+                        .map(|time| format_timestamp(time, self.relative_timestamps))
+                        .unwrap_or_else(|_| "?".to_string());
+
+                    Row::new([
+                        TableCell::from(
+                            Span::from(file_type).style(Style::default().fg(Color::Green)),
+                        ),
+                        TableCell::from(format!("{readable_size:.2}")),
+                        TableCell::from(modified),
+                        TableCell::from(self.highlight_filter(name, Style::default())),
+                    ])
+                } else {
+                    Row::new([
+                        TableCell::from(
+                            Span::from(file_type).style(Style::default().fg(Color::Green)),
+                        ),
+                        TableCell::from("?"),
+                        TableCell::from("?"),
+                        TableCell::from(self.highlight_filter(name, Style::default())),
+                    ])
+                }
+            })
+            .collect();
+
+        self.visible_rows
+            .set(area.height.saturating_sub(3) as usize);
+
+        let mut table = Table::new(file_rows, widths)
+            .block(self.block())
+            .header(Row::new(vec!["Type", "Size", "Modified", "Name"]));
+
+        if self.interactive {
+            if self.is_focused {
+                table = table
+                    .highlight_symbol(highlight_symbol)
+                    .highlight_style(Style::default().bg(Color::Blue));
+            }
+            f.render_stateful_widget(table, area, &mut self.table_state.borrow_mut());
+        } else {
+            let mut preview_state = TableState::default().with_offset(self.scroll_offset);
+            f.render_stateful_widget(table, area, &mut preview_state);
+        }
+    }
+}
+
+impl Drawable for FileExplorer {
+    fn draw(&self, f: &mut Frame, area: Rect) {
+        if self.modal.is_open() {
+            self.modal.draw(f, area);
+        } else if self.compact {
+            self.draw_grid(f, area);
+        } else {
+            self.draw_table(f, area);
+        }
+    }
+}
+
+impl Focusable for FileExplorer {
+    fn focus(&mut self) {
+        self.is_focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.is_focused = false;
+    }
+
+    fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+}
+
+impl InputHandler for FileExplorer {
+    fn handle_input(&mut self, key: Key) -> bool {
+        if self.modal.is_open() {
+            self.modal.handle_input(key.code);
+            if let Ok(task) = self.receiver.try_recv() {
+                let _ = self.apply_task(task);
+            }
+            true
+        } else {
+            if !self.current_dir.is_dir() {
+                let _ = self.refresh();
+            }
+            self.handle_command(key)
+        }
+    }
+}
+
+impl Editor for FileExplorer {
+    fn set_path(&mut self, new_dir: PathBuf) -> Result<()> {
+        self.load_dir(new_dir)?;
+        if self.interactive {
+            self.record_history();
+        }
+        Ok(())
+    }
+
+    fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.global_config.show_hidden = show_hidden;
+    }
+
+    fn get_commands_data(&self) -> Vec<(&'static str, &'static str)> {
+        self.get_commands()
+            .iter()
+            .filter(|c| {
+                self.compact || !matches!(c.id, "explorer.select_left" | "explorer.select_right")
+            })
+            .filter(|c| self.is_command_enabled(c.id))
+            .map(|c| (c.id, c.name))
+            .collect()
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+fn deleting(dir: &Path) -> String {
+    format!("Deleting {}", display_name(dir))
+}
+
+fn counting(dir: &Path) -> String {
+    format!("Counting items in {}", display_name(dir))
+}
+
+// Char range of the file stem within the displayed path, so prompts can
+// preselect the part of the name that is usually edited.
+fn stem_range(path: &Path) -> (usize, usize) {
+    let full_len = path.display().to_string().chars().count();
+    let name = display_name(path);
+    let start = full_len.saturating_sub(name.chars().count());
+    let stem_len = match path.file_stem() {
+        Some(stem) if !path.is_dir() => stem.to_string_lossy().chars().count(),
+        _ => name.chars().count(),
+    };
+    (start, start + stem_len)
+}
+
+fn name_column_width(area: Rect, widths: &[Constraint], highlight_symbol: &str) -> usize {
+    let inner_width = area
+        .width
+        .saturating_sub(2)
+        .saturating_sub(highlight_symbol.width() as u16);
+    let columns = Layout::horizontal(widths.to_vec())
+        .spacing(1)
+        .split(Rect::new(0, 0, inner_width, 1));
+    columns.last().map_or(0, |column| column.width as usize)
+}
+
+fn truncate_name(name: &str, max_width: usize) -> String {
+    let name_width = name.width();
+    if name_width <= max_width {
+        return name.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+
+    let ellipsis = '…';
+    let extension = match name.rfind('.') {
+        Some(dot) if dot > 0 => &name[dot..],
+        _ => "",
+    };
+    let extension_width = extension.width();
+
+    if !extension.is_empty() && extension_width + 2 <= max_width {
+        let stem = take_width(name, max_width - extension_width - 1);
+        format!("{stem}{ellipsis}{extension}")
+    } else {
+        let prefix = take_width(name, max_width - 1);
+        format!("{prefix}{ellipsis}")
+    }
+}
+
+fn take_width(text: &str, max_width: usize) -> String {
+    let mut width = 0;
+    text.chars()
+        .take_while(|c| {
+            width += c.width().unwrap_or(0);
+            width <= max_width
+        })
+        .collect()
+}
+
+fn pad_to_width(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text.width());
+    format!("{text}{}", " ".repeat(padding))
+}
+
+// Byte range of the first occurrence of `pattern` in `text`.
+fn find_match(text: &str, pattern: &str, case_sensitive: bool) -> Option<(usize, usize)> {
+    if pattern.is_empty() {
+        return None;
+    }
+    if case_sensitive {
+        return text
+            .find(pattern)
+            .map(|start| (start, start + pattern.len()));
+    }
+    let pattern: Vec<char> = pattern.chars().flat_map(char::to_lowercase).collect();
+    text.char_indices().find_map(|(start, _)| {
+        let mut lowered = text[start..]
+            .char_indices()
+            .flat_map(|(i, c)| c.to_lowercase().map(move |l| (i, c, l)));
+        let mut end = start;
+        for expected in &pattern {
+            let (i, c, l) = lowered.next()?;
+            if l != *expected {
+                return None;
+            }
+            end = start + i + c.len_utf8();
+        }
+        Some((start, end))
+    })
+}
+
+fn format_timestamp(time: SystemTime, relative: bool) -> String {
+    if !relative {
+        return DateTime::<Local>::from(time)
+            .format("%Y-%m-%d %H:%M")
+            .to_string();
+    }
+
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (amount, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => (seconds / 60, "minute"),
+        3_600..=86_399 => (seconds / 3_600, "hour"),
+        86_400..=2_591_999 => (seconds / 86_400, "day"),
+        2_592_000..=31_535_999 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{amount} {unit}{plural} ago")
+}
+
+const DELETE_PROGRESS_STEP: usize = 500;
+const HISTORY_LIMIT: usize = 100;
+
+const MUTATING_COMMANDS: [&str; 9] = [
+    "explorer.run_command",
+    "explorer.delete_current_file",
+    "explorer.move_current_file",
+    "explorer.rename_current_file",
+    "explorer.copy_current_file",
+    "explorer.paste",
+    "explorer.create_file",
+    "explorer.create_and_enter",
+    "explorer.new_from_template",
+];
+
+#[cfg(windows)]
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!("Name cannot be empty");
+    }
+    if name == "." || name == ".." {
+        bail!("Name cannot be '{}'", name);
+    }
+    if name.contains(std::path::is_separator) {
+        bail!("Name cannot contain path separators");
+    }
+    if name.contains('\0') {
+        bail!("Name cannot contain NUL bytes");
+    }
+    #[cfg(windows)]
+    {
+        if let Some(c) = name
+            .chars()
+            .find(|c| "<>:\"|?*".contains(*c) || c.is_control())
+        {
+            bail!("Name cannot contain '{}'", c);
+        }
+        if name.ends_with('.') || name.ends_with(' ') {
+            bail!("Name cannot end with a dot or a space");
+        }
+        let stem = name.split('.').next().unwrap_or(name);
+        if RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+        {
+            bail!("'{}' is a reserved name", stem);
+        }
+    }
+    Ok(())
+}
+
+// Like `mv file dir/`: an existing directory target keeps the original name.
+fn destination(original: &Path, target: &str) -> PathBuf {
+    let mut destination = PathBuf::from(target);
+    if destination.is_dir() && destination != original {
+        if let Some(name) = original.file_name() {
+            destination.push(name);
+        }
+    }
+    destination
+}
+
+// Whether both paths name the same directory entry, however they are spelled.
+// Only the parents are resolved, so a symlink is not its target.
+fn is_same_location(first: &Path, second: &Path) -> bool {
+    let resolve = |path: &Path| {
+        let parent = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        Some(parent.canonicalize().ok()?.join(path.file_name()?))
+    };
+    first == second || resolve(first).is_some_and(|first| Some(first) == resolve(second))
+}
+
+fn is_same_or_inside(source: &Path, destination: &Path) -> bool {
+    let Ok(source) = source.canonicalize() else {
+        return false;
+    };
+    let mut existing = destination;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return false,
+        }
+        if existing.as_os_str().is_empty() {
+            existing = Path::new(".");
+        }
+    }
+    let Ok(mut destination) = existing.canonicalize() else {
+        return false;
+    };
+    destination.extend(missing.iter().rev());
+    destination.starts_with(source)
+}
+
+fn is_nonempty_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+        && fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+// Stops early with partial counts once `cancelled` is set.
+fn count_recursive(dir: &Path, show_hidden: bool, cancelled: &AtomicBool) -> (usize, usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    let mut counts = (0, 0);
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if is_cancelled(cancelled) {
+            break;
+        }
+        if !show_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {
+                let (files, dirs) = count_recursive(&entry.path(), show_hidden, cancelled);
+                counts.0 += files;
+                counts.1 += dirs + 1;
+            }
+            _ => counts.0 += 1,
+        }
+    }
+    counts
+}
+
+// Like fs::remove_dir_all, but reports every removed entry and stops with
+// an `Interrupted` error once `cancelled` is set.
+fn remove_tree(
+    dir: &Path,
+    cancelled: &AtomicBool,
+    on_removed: &mut dyn FnMut(),
+) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        if is_cancelled(cancelled) {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_tree(&entry.path(), cancelled, on_removed)?;
+        } else {
+            fs::remove_file(entry.path())?;
+            on_removed();
+        }
+    }
+    fs::remove_dir(dir)?;
+    on_removed();
+    Ok(())
+}
+
+fn copy_tree(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let file_type = fs::symlink_metadata(source)?.file_type();
+    if file_type.is_dir() {
+        fs::create_dir(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_tree(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if file_type.is_symlink() {
+        return std::os::unix::fs::symlink(fs::read_link(source)?, destination);
+    }
+    fs::copy(source, destination).map(|_| ())
+}
+
+// fs::rename cannot move between file systems, so that case copies the tree
+// and removes the source once the copy is complete.
+fn move_tree(source: &Path, destination: &Path) -> std::io::Result<()> {
+    match fs::rename(source, destination) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            copy_then_remove(source, destination)
+        }
+        result => result,
+    }
+}
+
+fn copy_then_remove(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if let Err(e) = copy_tree(source, destination) {
+        let _ = remove_path(destination);
+        return Err(e);
+    }
+    remove_path(source)
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn read_dir_entries(dir: &PathBuf) -> Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .context("Could not read directory entries")?
+        .filter(|res| res.is_ok())
+        .map(|res| res.unwrap().path())
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+impl CommandHandler for FileExplorer {
+    fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    fn is_command_enabled(&self, command_id: &str) -> bool {
+        !(self.global_config.read_only && MUTATING_COMMANDS.contains(&command_id))
+    }
+    fn get_commands(&self) -> &'static [Command<Self>] {
         if !self.interactive {
-            vec![]
+            &[
+                Command {
+                    id: "preview_explorer.scroll_up",
+                    name: "Scroll up",
+                    func: FileExplorer::scroll_up,
+                },
+                Command {
+                    id: "preview_explorer.scroll_down",
+                    name: "Scroll down",
+                    func: FileExplorer::scroll_down,
+                },
+                Command {
+                    id: "preview_explorer.page_up",
+                    name: "Page up",
+                    func: FileExplorer::page_up,
+                },
+                Command {
+                    id: "preview_explorer.page_down",
+                    name: "Page down",
+                    func: FileExplorer::page_down,
+                },
+            ]
         } else {
-            vec![
+            &[
                 Command {
                     id: "explorer.select_previous_file",
                     name: "Prev file",
@@ -411,6 +1883,31 @@ impl CommandHandler for FileExplorer {
                     name: "Back",
                     func: FileExplorer::go_back,
                 },
+                Command {
+                    id: "explorer.history_back",
+                    name: "History back",
+                    func: FileExplorer::history_back,
+                },
+                Command {
+                    id: "explorer.history_forward",
+                    name: "History forward",
+                    func: FileExplorer::history_forward,
+                },
+                Command {
+                    id: "explorer.next_sibling_dir",
+                    name: "Next dir",
+                    func: FileExplorer::next_sibling_dir,
+                },
+                Command {
+                    id: "explorer.prev_sibling_dir",
+                    name: "Prev dir",
+                    func: FileExplorer::prev_sibling_dir,
+                },
+                Command {
+                    id: "explorer.ancestor_up",
+                    name: "Parent dirs",
+                    func: FileExplorer::select_ancestor,
+                },
                 Command {
                     id: "explorer.open_selected_file",
                     name: "Open file",
@@ -422,26 +1919,980 @@ impl CommandHandler for FileExplorer {
                     func: FileExplorer::prompt_for_delete_current_file,
                 },
                 Command {
-                    id: "explorer.move_current_file",
-                    name: "Move file",
-                    func: FileExplorer::prompt_for_move_file,
+                    id: "explorer.move_current_file",
+                    name: "Move file",
+                    func: FileExplorer::prompt_for_move_file,
+                },
+                Command {
+                    id: "explorer.rename_current_file",
+                    name: "Rename",
+                    func: FileExplorer::prompt_for_rename,
+                },
+                Command {
+                    id: "explorer.copy_current_file",
+                    name: "Copy file",
+                    func: FileExplorer::prompt_for_copy_file,
+                },
+                Command {
+                    id: "explorer.yank",
+                    name: "Yank",
+                    func: FileExplorer::yank_file,
+                },
+                Command {
+                    id: "explorer.cut",
+                    name: "Cut",
+                    func: FileExplorer::cut_file,
+                },
+                Command {
+                    id: "explorer.paste",
+                    name: "Paste",
+                    func: FileExplorer::paste_file,
                 },
                 Command {
                     id: "explorer.sort_entries",
                     name: "Sort",
                     func: FileExplorer::prompt_for_sorting_criterion,
                 },
+                Command {
+                    id: "explorer.toggle_sort_direction",
+                    name: "Reverse sort",
+                    func: FileExplorer::toggle_sort_direction,
+                },
+                Command {
+                    id: "explorer.toggle_dirs_first",
+                    name: "Dirs first",
+                    func: FileExplorer::toggle_dirs_first,
+                },
                 Command {
                     id: "explorer.create_file",
                     name: "New file",
                     func: FileExplorer::prompt_for_new_file,
                 },
+                Command {
+                    id: "explorer.create_and_enter",
+                    name: "New dir and enter",
+                    func: FileExplorer::prompt_for_new_dir_and_enter,
+                },
+                Command {
+                    id: "explorer.new_from_template",
+                    name: "New from template",
+                    func: FileExplorer::prompt_for_template,
+                },
+                Command {
+                    id: "explorer.run_command",
+                    name: "Run command",
+                    func: FileExplorer::prompt_for_command,
+                },
                 Command {
                     id: "explorer.filter",
                     name: "Filter",
                     func: FileExplorer::prompt_for_new_filter,
                 },
+                Command {
+                    id: "explorer.toggle_timestamp_format",
+                    name: "Relative dates",
+                    func: FileExplorer::toggle_timestamp_format,
+                },
+                Command {
+                    id: "explorer.toggle_hidden",
+                    name: "Toggle hidden",
+                    func: FileExplorer::toggle_hidden,
+                },
+                Command {
+                    id: "explorer.item_count",
+                    name: "Item count",
+                    func: FileExplorer::show_item_count,
+                },
+                Command {
+                    id: "explorer.cancel_background",
+                    name: "Cancel tasks",
+                    func: FileExplorer::cancel_background,
+                },
+                Command {
+                    id: "explorer.toggle_view",
+                    name: "Compact view",
+                    func: FileExplorer::toggle_view,
+                },
+                Command {
+                    id: "explorer.select_left",
+                    name: "Left column",
+                    func: FileExplorer::select_left,
+                },
+                Command {
+                    id: "explorer.select_right",
+                    name: "Right column",
+                    func: FileExplorer::select_right,
+                },
+                Command {
+                    id: "explorer.goto_target",
+                    name: "Go to link target",
+                    func: FileExplorer::goto_target,
+                },
+                Command {
+                    id: "explorer.copy_path",
+                    name: "Copy path",
+                    func: FileExplorer::copy_path,
+                },
+                Command {
+                    id: "explorer.copy_name",
+                    name: "Copy name",
+                    func: FileExplorer::copy_name,
+                },
+                Command {
+                    id: "explorer.copy_relative_path",
+                    name: "Copy relative path",
+                    func: FileExplorer::copy_relative_path,
+                },
             ]
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use tempfile::TempDir;
+
+    fn explorer(entries: &[&str]) -> (TempDir, FileExplorer) {
+        explorer_with(entries, &Config::default())
+    }
+
+    // Entries ending with '/' are created as directories, the rest as files
+    // containing their own name.
+    fn explorer_with(entries: &[&str], config: &Config) -> (TempDir, FileExplorer) {
+        let dir = tempfile::tempdir().unwrap();
+        for entry in entries {
+            let path = dir.path().join(entry);
+            if entry.ends_with('/') {
+                fs::create_dir_all(path).unwrap();
+            } else {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, entry).unwrap();
+            }
+        }
+        let explorer =
+            FileExplorer::new("explorer", true, config, dir.path().to_path_buf()).unwrap();
+        (dir, explorer)
+    }
+
+    fn names(explorer: &FileExplorer) -> Vec<String> {
+        explorer
+            .entries
+            .iter()
+            .map(|entry| display_name(entry))
+            .collect()
+    }
+
+    fn selected_name(explorer: &FileExplorer) -> Option<String> {
+        explorer.get_selected_file().map(|file| display_name(&file))
+    }
+
+    #[test]
+    fn create_file_adds_the_entry() {
+        let (dir, mut explorer) = explorer(&["a.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::CreateFile("b.txt".to_string()))
+            .unwrap();
+
+        assert!(dir.path().join("b.txt").is_file());
+        assert_eq!(names(&explorer), ["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn delete_file_removes_the_entry() {
+        let (dir, mut explorer) = explorer(&["a.txt", "b.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::DeleteFile(dir.path().join("a.txt")))
+            .unwrap();
+
+        assert!(!dir.path().join("a.txt").exists());
+        assert_eq!(names(&explorer), ["b.txt"]);
+    }
+
+    #[test]
+    fn move_file_into_a_directory() {
+        let config = Config {
+            confirm_move: false,
+            ..Config::default()
+        };
+        let (dir, mut explorer) = explorer_with(&["a.txt", "sub/"], &config);
+        let target = dir.path().join("sub");
+
+        explorer
+            .apply_task(ExplorerTask::MoveFile(
+                dir.path().join("a.txt"),
+                target.display().to_string(),
+            ))
+            .unwrap();
+
+        assert!(target.join("a.txt").is_file());
+        assert_eq!(names(&explorer), ["sub"]);
+    }
+
+    #[test]
+    fn move_file_asks_for_confirmation_by_default() {
+        let (dir, mut explorer) = explorer(&["a.txt", "sub/"]);
+
+        explorer
+            .apply_task(ExplorerTask::MoveFile(
+                dir.path().join("a.txt"),
+                dir.path().join("sub").display().to_string(),
+            ))
+            .unwrap();
+
+        assert!(explorer.modal.is_open());
+        assert!(dir.path().join("a.txt").is_file());
+    }
+
+    #[test]
+    fn sort_orders_by_the_chosen_criterion() {
+        let (dir, mut explorer) = explorer(&[]);
+        fs::write(dir.path().join("a"), "1").unwrap();
+        fs::write(dir.path().join("b"), "12345").unwrap();
+        fs::write(dir.path().join("c"), "123").unwrap();
+
+        explorer.apply_task(ExplorerTask::Sort(1)).unwrap();
+        assert_eq!(names(&explorer), ["b", "c", "a"]);
+
+        explorer.apply_task(ExplorerTask::Sort(0)).unwrap();
+        assert_eq!(names(&explorer), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn filter_keeps_matching_entries() {
+        let (_dir, mut explorer) = explorer(&["notes.md", "main.rs", "Readme.MD"]);
+
+        explorer
+            .apply_task(ExplorerTask::Filter(".md".to_string()))
+            .unwrap();
+        assert_eq!(names(&explorer), ["Readme.MD", "notes.md"]);
+
+        explorer
+            .apply_task(ExplorerTask::Filter(String::new()))
+            .unwrap();
+        assert_eq!(names(&explorer), ["Readme.MD", "main.rs", "notes.md"]);
+    }
+
+    #[test]
+    fn first_listing_matches_a_refreshed_listing() {
+        let config = Config {
+            default_sort: String::from("size"),
+            ..Config::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("z_dir")).unwrap();
+        fs::write(dir.path().join("a"), "1").unwrap();
+        fs::write(dir.path().join("b"), "12345").unwrap();
+        fs::write(dir.path().join("c"), "123").unwrap();
+
+        let mut explorer =
+            FileExplorer::new("explorer", true, &config, dir.path().to_path_buf()).unwrap();
+        let first = names(&explorer);
+        explorer.refresh().unwrap();
+
+        assert_eq!(first, ["z_dir", "b", "c", "a"]);
+        assert_eq!(names(&explorer), first);
+    }
+
+    #[test]
+    fn a_deleted_current_dir_falls_back_to_the_nearest_ancestor() {
+        let (dir, mut explorer) = explorer(&["a/b/c/", "a/file.txt"]);
+        explorer.set_path(dir.path().join("a/b/c")).unwrap();
+        fs::remove_dir_all(dir.path().join("a/b")).unwrap();
+
+        explorer.refresh().unwrap();
+
+        assert_eq!(explorer.current_dir, dir.path().join("a"));
+        assert_eq!(names(&explorer), ["file.txt"]);
+        assert!(explorer
+            .take_notification()
+            .is_some_and(|message| message.ends_with("no longer exists")));
+    }
+
+    #[test]
+    fn is_same_or_inside_detects_self_and_descendants() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a");
+        fs::create_dir_all(a.join("b")).unwrap();
+        fs::create_dir(dir.path().join("ab")).unwrap();
+
+        assert!(is_same_or_inside(&a, &a));
+        assert!(is_same_or_inside(&a, &a.join("b")));
+        assert!(is_same_or_inside(&a, &a.join("b/new/deeper")));
+        assert!(is_same_or_inside(&a, &a.join("b/..")));
+        assert!(!is_same_or_inside(&a, &dir.path().join("ab")));
+        assert!(!is_same_or_inside(&a, &dir.path().join("a_copy")));
+        assert!(!is_same_or_inside(&a.join("b"), &a));
+    }
+
+    #[test]
+    fn moving_a_directory_into_itself_is_refused() {
+        let config = Config {
+            confirm_move: false,
+            ..Config::default()
+        };
+        let (dir, mut explorer) = explorer_with(&["a/b/"], &config);
+        let a = dir.path().join("a");
+
+        explorer
+            .apply_task(ExplorerTask::MoveFile(
+                a.clone(),
+                a.join("b").display().to_string(),
+            ))
+            .unwrap();
+
+        assert!(explorer.modal.is_open());
+        assert!(a.join("b").is_dir());
+        assert!(!a.join("b/a").exists());
+    }
+
+    #[test]
+    fn validate_name_rejects_invalid_names() {
+        for name in ["", "  ", ".", "..", "a/b", "nul\0byte"] {
+            assert!(validate_name(name).is_err(), "{name:?} should be rejected");
+        }
+        for name in ["file.txt", ".hidden", "with space", "café"] {
+            assert!(validate_name(name).is_ok(), "{name:?} should be accepted");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn validate_name_allows_windows_only_restrictions_on_unix() {
+        for name in ["CON", "a:b", "what?", "trailing.", "..."] {
+            assert!(validate_name(name).is_ok(), "{name:?} should be accepted");
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn validate_name_rejects_windows_reserved_names() {
+        for name in [
+            "CON",
+            "prn.txt",
+            "Com1",
+            "LPT9.log",
+            "a:b",
+            "a\\b",
+            "what?",
+            "trailing.",
+        ] {
+            assert!(validate_name(name).is_err(), "{name:?} should be rejected");
+        }
+        for name in ["CONSOLE", "COM10", "printer.txt"] {
+            assert!(validate_name(name).is_ok(), "{name:?} should be accepted");
+        }
+    }
+
+    #[test]
+    fn stem_range_selects_the_name_without_its_extension() {
+        let (dir, _explorer) = explorer(&["report.pdf", "archive.tar.gz", ".bashrc", "dir.d/"]);
+        let selected = |name: &str| {
+            let path = dir.path().join(name);
+            let (start, end) = stem_range(&path);
+            path.display()
+                .to_string()
+                .chars()
+                .skip(start)
+                .take(end - start)
+                .collect::<String>()
+        };
+
+        assert_eq!(selected("report.pdf"), "report");
+        assert_eq!(selected("archive.tar.gz"), "archive.tar");
+        assert_eq!(selected(".bashrc"), ".bashrc");
+        assert_eq!(selected("dir.d"), "dir.d");
+    }
+
+    #[test]
+    fn directory_deletes_are_tracked_until_they_finish() {
+        let config = Config {
+            confirm_delete: false,
+            ..Config::default()
+        };
+        let (dir, mut explorer) = explorer_with(&["sub/a.txt", "b.txt"], &config);
+
+        explorer
+            .apply_task(ExplorerTask::DeleteFile(dir.path().join("sub")))
+            .unwrap();
+        assert_eq!(explorer.background_tasks(), ["Deleting sub"]);
+
+        let finished = explorer.receiver.recv().unwrap();
+        explorer.apply_task(finished).unwrap();
+        assert!(explorer.background_tasks().is_empty());
+        assert_eq!(names(&explorer), ["b.txt"]);
+    }
+
+    #[test]
+    fn a_cancelled_delete_stops_before_removing_anything() {
+        let (dir, _explorer) = explorer(&["sub/a.txt", "sub/nested/b.txt"]);
+        let cancelled = AtomicBool::new(true);
+        let mut removed = 0;
+
+        let result = remove_tree(&dir.path().join("sub"), &cancelled, &mut || removed += 1);
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::Interrupted);
+        assert_eq!(removed, 0);
+        assert!(dir.path().join("sub/nested/b.txt").is_file());
+    }
+
+    #[test]
+    fn a_cancelled_count_returns_partial_results() {
+        let (dir, _explorer) = explorer(&["a.txt", "sub/b.txt", "sub/c.txt"]);
+
+        let counts = count_recursive(dir.path(), true, &AtomicBool::new(false));
+        assert_eq!(counts, (3, 1));
+
+        let counts = count_recursive(dir.path(), true, &AtomicBool::new(true));
+        assert_eq!(counts, (0, 0));
+    }
+
+    #[test]
+    fn esc_goes_up_without_cancelling_background_operations() {
+        let (dir, mut explorer) = explorer(&["sub/"]);
+        explorer.set_path(dir.path().join("sub")).unwrap();
+        let cancelled = explorer.background.start(deleting(&dir.path().join("x")));
+
+        explorer.go_back(KeyCode::Esc);
+
+        assert!(!is_cancelled(&cancelled));
+        assert_eq!(explorer.current_dir, dir.path());
+    }
+
+    #[test]
+    fn background_operations_are_cancelled_after_confirming() {
+        let (dir, mut explorer) = explorer(&[]);
+        let cancelled = explorer.background.start(deleting(&dir.path().join("x")));
+
+        explorer.cancel_background(KeyCode::Char('X'));
+        assert!(explorer.modal.is_open());
+        assert!(!is_cancelled(&cancelled));
+
+        explorer.modal.handle_input(KeyCode::Char('y'));
+        explorer.poll_tasks();
+        assert!(is_cancelled(&cancelled));
+    }
+
+    #[test]
+    fn declining_the_cancel_prompt_keeps_operations_running() {
+        let (dir, mut explorer) = explorer(&[]);
+        let cancelled = explorer.background.start(deleting(&dir.path().join("x")));
+
+        explorer.cancel_background(KeyCode::Char('X'));
+        explorer.modal.handle_input(KeyCode::Char('n'));
+        explorer.poll_tasks();
+
+        assert!(!is_cancelled(&cancelled));
+    }
+
+    #[test]
+    fn name_scope_filters_on_the_file_name_only() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt", "sub/sub.md"]);
+        explorer.set_path(dir.path().join("sub")).unwrap();
+
+        explorer
+            .apply_task(ExplorerTask::Filter("sub".to_string()))
+            .unwrap();
+
+        assert_eq!(names(&explorer), ["sub.md"]);
+    }
+
+    #[test]
+    fn path_scope_filters_on_the_path_below_the_relative_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            filter_scope: FilterScope::Path,
+            relative_root: Some(dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        fs::create_dir_all(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/a.txt"), "").unwrap();
+        fs::write(dir.path().join("sub/b.md"), "").unwrap();
+        let mut explorer =
+            FileExplorer::new("explorer", true, &config, dir.path().join("sub")).unwrap();
+
+        explorer
+            .apply_task(ExplorerTask::Filter("SUB/".to_string()))
+            .unwrap();
+        assert_eq!(names(&explorer), ["a.txt", "b.md"]);
+
+        explorer
+            .apply_task(ExplorerTask::Filter("sub/a".to_string()))
+            .unwrap();
+        assert_eq!(names(&explorer), ["a.txt"]);
+    }
+
+    #[test]
+    fn select_column_moves_by_a_column_of_rows_within_bounds() {
+        let files = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let (_dir, mut explorer) = explorer(&files);
+        assert!(!explorer.select_right(KeyCode::Right));
+
+        explorer.toggle_view(KeyCode::Char('v'));
+        explorer.grid_rows.set(3);
+        explorer.select(4);
+
+        assert!(explorer.select_right(KeyCode::Right));
+        assert_eq!(explorer.selected_index(), 7);
+        explorer.select_right(KeyCode::Right);
+        assert_eq!(explorer.selected_index(), 7);
+
+        explorer.select_left(KeyCode::Left);
+        assert_eq!(explorer.selected_index(), 4);
+        explorer.select_left(KeyCode::Left);
+        assert_eq!(explorer.selected_index(), 1);
+        explorer.select_left(KeyCode::Left);
+        assert_eq!(explorer.selected_index(), 1);
+    }
+
+    #[test]
+    fn select_column_stays_put_when_the_target_column_is_short() {
+        let (_dir, mut explorer) = explorer(&["a", "b", "c", "d", "e"]);
+        explorer.toggle_view(KeyCode::Char('v'));
+        explorer.grid_rows.set(3);
+        explorer.select(2);
+
+        explorer.select_right(KeyCode::Right);
+        assert_eq!(explorer.selected_index(), 2);
+    }
+
+    #[test]
+    fn esc_clears_the_filter_before_going_up() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt", "sub/b.md"]);
+        explorer.set_path(dir.path().join("sub")).unwrap();
+        explorer
+            .apply_task(ExplorerTask::Filter(".md".to_string()))
+            .unwrap();
+
+        explorer.go_back(KeyCode::Esc);
+        assert_eq!(explorer.current_dir, dir.path().join("sub"));
+        assert_eq!(explorer.name_filter(), "");
+        assert_eq!(names(&explorer), ["a.txt", "b.md"]);
+
+        explorer.go_back(KeyCode::Esc);
+        assert_eq!(explorer.current_dir, dir.path());
+    }
+
+    #[test]
+    fn esc_leaves_ancestor_selection_before_clearing_the_filter() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt"]);
+        explorer.set_path(dir.path().join("sub")).unwrap();
+        explorer
+            .apply_task(ExplorerTask::Filter("a".to_string()))
+            .unwrap();
+        explorer.select_ancestor(KeyCode::Char('u'));
+
+        explorer.go_back(KeyCode::Esc);
+        assert_eq!(explorer.ancestor_levels, None);
+        assert_eq!(explorer.name_filter(), "a");
+
+        explorer.go_back(KeyCode::Esc);
+        assert_eq!(explorer.name_filter(), "");
+        assert_eq!(explorer.current_dir, dir.path().join("sub"));
+    }
+
+    #[test]
+    fn a_created_file_is_selected() {
+        let (_dir, mut explorer) = explorer(&["a.txt", "c.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::CreateFile("b.txt".to_string()))
+            .unwrap();
+
+        assert_eq!(selected_name(&explorer).as_deref(), Some("b.txt"));
+    }
+
+    #[test]
+    fn a_created_directory_is_selected() {
+        let (_dir, mut explorer) = explorer(&["a.txt", "c.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::CreateFile("z/".to_string()))
+            .unwrap();
+
+        assert_eq!(selected_name(&explorer).as_deref(), Some("z"));
+    }
+
+    #[test]
+    fn a_renamed_file_is_selected() {
+        let (dir, mut explorer) = explorer(&["a.txt", "b.txt", "c.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::RenameFile(
+                dir.path().join("a.txt"),
+                "d.txt".to_string(),
+            ))
+            .unwrap();
+
+        assert_eq!(names(&explorer), ["b.txt", "c.txt", "d.txt"]);
+        assert_eq!(selected_name(&explorer).as_deref(), Some("d.txt"));
+    }
+
+    #[test]
+    fn a_moved_file_is_selected() {
+        let config = Config {
+            confirm_move: false,
+            ..Config::default()
+        };
+        let (dir, mut explorer) = explorer_with(&["a.txt", "b.txt"], &config);
+
+        explorer
+            .apply_task(ExplorerTask::MoveFile(
+                dir.path().join("a.txt"),
+                dir.path().join("z.txt").display().to_string(),
+            ))
+            .unwrap();
+
+        assert_eq!(selected_name(&explorer).as_deref(), Some("z.txt"));
+    }
+
+    #[test]
+    fn truncate_name_counts_double_width_characters() {
+        assert_eq!(truncate_name("漢字", 4), "漢字");
+        assert_eq!(truncate_name("漢字漢字", 5), "漢字…");
+        assert_eq!(truncate_name("漢字のファイル.txt", 10), "漢字….txt");
+    }
+
+    #[test]
+    fn take_width_never_splits_a_wide_character() {
+        assert_eq!(take_width("漢字", 3), "漢");
+        assert_eq!(take_width("a漢字", 4), "a漢");
+    }
+
+    #[test]
+    fn commands_run_in_the_background_and_report_their_output() {
+        let (dir, mut explorer) = explorer(&["a.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::RunCommand(
+                "echo hi> out.txt && echo done".to_string(),
+            ))
+            .unwrap();
+        assert!(!explorer.modal.is_open());
+
+        let finished = explorer.receiver.recv().unwrap();
+        assert!(matches!(&finished, ExplorerTask::ShowInfo(message) if message == "done"));
+        explorer.apply_task(finished).unwrap();
+        assert!(explorer.modal.is_open());
+        assert!(dir.path().join("out.txt").is_file());
+        assert_eq!(names(&explorer), ["a.txt", "out.txt"]);
+    }
+
+    #[test]
+    fn create_and_enter_moves_into_the_new_directory() {
+        let (dir, mut explorer) = explorer(&["a.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::CreateAndEnter("new/nested".to_string()))
+            .unwrap();
+
+        assert!(dir.path().join("new/nested").is_dir());
+        assert_eq!(explorer.current_dir, dir.path().join("new").join("nested"));
+        assert!(names(&explorer).is_empty());
+    }
+
+    #[test]
+    fn create_and_enter_stays_put_when_creation_fails() {
+        let (dir, mut explorer) = explorer(&["a.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::CreateAndEnter("a.txt/sub".to_string()))
+            .unwrap();
+
+        assert!(explorer.modal.is_open());
+        assert_eq!(explorer.current_dir, dir.path());
+    }
+
+    #[test]
+    fn moving_onto_an_existing_directory_keeps_the_name() {
+        let config = Config {
+            confirm_move: false,
+            ..Config::default()
+        };
+        let (dir, mut explorer) = explorer_with(&["a.txt", "sub/b.txt"], &config);
+
+        explorer
+            .apply_task(ExplorerTask::MoveFile(
+                dir.path().join("a.txt"),
+                format!("{}/", dir.path().join("sub").display()),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.path().join("sub/a.txt")).unwrap(),
+            "a.txt"
+        );
+        assert!(!dir.path().join("a.txt").exists());
+    }
+
+    #[test]
+    fn copying_onto_an_existing_directory_keeps_the_name() {
+        let (dir, mut explorer) = explorer(&["a.txt", "sub/"]);
+
+        explorer
+            .apply_task(ExplorerTask::CopyFile(
+                dir.path().join("a.txt"),
+                dir.path().join("sub").display().to_string(),
+            ))
+            .unwrap();
+
+        assert!(dir.path().join("sub/a.txt").is_file());
+        assert!(dir.path().join("a.txt").is_file());
+    }
+
+    #[test]
+    fn destination_only_joins_the_name_for_existing_directories() {
+        let (dir, _explorer) = explorer(&["a.txt", "sub/"]);
+        let original = dir.path().join("a.txt");
+
+        assert_eq!(
+            destination(&original, &dir.path().join("sub").display().to_string()),
+            dir.path().join("sub").join("a.txt")
+        );
+        assert_eq!(
+            destination(&original, &dir.path().join("b.txt").display().to_string()),
+            dir.path().join("b.txt")
+        );
+        assert_eq!(
+            destination(&original, &original.display().to_string()),
+            original
+        );
+    }
+
+    #[test]
+    fn a_mismatched_typed_name_aborts_the_delete() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::TypedDelete(
+                dir.path().join("sub"),
+                "sib".to_string(),
+            ))
+            .unwrap();
+
+        assert!(explorer.modal.is_open());
+        assert!(explorer.background_tasks().is_empty());
+        assert!(dir.path().join("sub/a.txt").is_file());
+    }
+
+    #[test]
+    fn the_typed_name_of_a_non_empty_directory_deletes_it() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt", "b.txt"]);
+
+        explorer
+            .apply_task(ExplorerTask::TypedDelete(
+                dir.path().join("sub"),
+                "sub".to_string(),
+            ))
+            .unwrap();
+        let finished = explorer.receiver.recv().unwrap();
+        explorer.apply_task(finished).unwrap();
+
+        assert!(!dir.path().join("sub").exists());
+        assert_eq!(names(&explorer), ["b.txt"]);
+    }
+
+    #[test]
+    fn a_new_branch_drops_the_forward_history() {
+        let (dir, mut explorer) = explorer(&["a/", "b/c/"]);
+        let alt_left = Key::new(KeyCode::Left, KeyModifiers::ALT);
+        let alt_right = Key::new(KeyCode::Right, KeyModifiers::ALT);
+
+        explorer.set_path(dir.path().join("a")).unwrap();
+        explorer.handle_input(alt_left);
+        assert_eq!(explorer.current_dir, dir.path());
+
+        explorer.set_path(dir.path().join("b")).unwrap();
+        explorer.handle_input(alt_right);
+        assert_eq!(explorer.current_dir, dir.path().join("b"));
+
+        explorer.set_path(dir.path().join("b").join("c")).unwrap();
+        explorer.handle_input(alt_left);
+        assert_eq!(explorer.current_dir, dir.path().join("b"));
+        explorer.handle_input(alt_left);
+        assert_eq!(explorer.current_dir, dir.path());
+        explorer.handle_input(alt_left);
+        assert_eq!(explorer.current_dir, dir.path());
+    }
+
+    #[test]
+    fn history_forward_retraces_the_branch_after_going_back() {
+        let (dir, mut explorer) = explorer(&["a/", "b/c/"]);
+        explorer.set_path(dir.path().join("a")).unwrap();
+        explorer.history_back(KeyCode::Char('H'));
+        explorer.set_path(dir.path().join("b")).unwrap();
+        explorer.set_path(dir.path().join("b").join("c")).unwrap();
+
+        explorer.history_back(KeyCode::Char('H'));
+        explorer.history_back(KeyCode::Char('H'));
+        assert_eq!(explorer.current_dir, dir.path());
+
+        explorer.history_forward(KeyCode::Char('L'));
+        assert_eq!(explorer.current_dir, dir.path().join("b"));
+        explorer.history_forward(KeyCode::Char('L'));
+        assert_eq!(explorer.current_dir, dir.path().join("b").join("c"));
+        explorer.history_forward(KeyCode::Char('L'));
+        assert_eq!(explorer.current_dir, dir.path().join("b").join("c"));
+    }
+
+    #[test]
+    fn copy_then_remove_moves_a_whole_tree() {
+        let (dir, _explorer) = explorer(&["sub/a.txt", "sub/nested/b.txt", "target/"]);
+        let destination = dir.path().join("target").join("sub");
+
+        copy_then_remove(&dir.path().join("sub"), &destination).unwrap();
+
+        assert!(!dir.path().join("sub").exists());
+        assert_eq!(
+            fs::read_to_string(destination.join("nested/b.txt")).unwrap(),
+            "sub/nested/b.txt"
+        );
+    }
+
+    #[test]
+    fn a_failed_copy_keeps_the_source_and_removes_the_partial_copy() {
+        let (dir, _explorer) = explorer(&["sub/a.txt"]);
+        let destination = dir.path().join("missing").join("sub");
+
+        assert!(copy_then_remove(&dir.path().join("sub"), &destination).is_err());
+
+        assert!(dir.path().join("sub/a.txt").is_file());
+        assert!(!destination.exists());
+    }
+
+    #[test]
+    fn a_cut_paste_moves_the_entry_and_empties_the_clipboard() {
+        let (dir, mut explorer) = explorer(&["a.txt", "sub/"]);
+        explorer.file_clipboard = Some((dir.path().join("a.txt"), ClipboardOp::Cut));
+        explorer.set_path(dir.path().join("sub")).unwrap();
+
+        explorer.paste_file(KeyCode::Char('p'));
+
+        assert!(dir.path().join("sub/a.txt").is_file());
+        assert!(!dir.path().join("a.txt").exists());
+        assert!(explorer.file_clipboard.is_none());
+    }
+
+    #[test]
+    fn a_single_y_yanks_the_selected_entry() {
+        let (_dir, mut explorer) = explorer(&["a.txt"]);
+        let selected = explorer.get_selected_file().unwrap();
+
+        explorer.handle_input(KeyCode::Char('y').into());
+
+        assert_eq!(explorer.file_clipboard, Some((selected, ClipboardOp::Copy)));
+    }
+
+    #[test]
+    fn dotfiles_are_hidden_until_toggled() {
+        let (_dir, mut explorer) = explorer(&[".hidden", "visible.txt"]);
+        assert_eq!(names(&explorer), ["visible.txt"]);
+
+        explorer.toggle_hidden(KeyCode::Char('.'));
+        assert_eq!(names(&explorer), [".hidden", "visible.txt"]);
+
+        explorer.toggle_hidden(KeyCode::Char('.'));
+        assert_eq!(names(&explorer), ["visible.txt"]);
+    }
+
+    // Types `text` over the prompt's preselection, confirms and runs the task.
+    fn answer_prompt(explorer: &mut FileExplorer, text: &str) {
+        for c in text.chars() {
+            explorer.modal.handle_input(KeyCode::Char(c));
+        }
+        explorer.modal.handle_input(KeyCode::Enter);
+        let task = explorer.receiver.try_recv().unwrap();
+        explorer.apply_task(task).unwrap();
+    }
+
+    #[test]
+    fn rename_move_and_copy_prompts_preselect_the_stem() {
+        let config = Config {
+            confirm_move: false,
+            ..Config::default()
+        };
+        let (_dir, mut explorer) = explorer_with(&["report.pdf"], &config);
+
+        explorer.prompt_for_rename(KeyCode::Char('r'));
+        answer_prompt(&mut explorer, "a");
+        assert_eq!(names(&explorer), ["a.pdf"]);
+
+        explorer.prompt_for_move_file(KeyCode::Char('m'));
+        answer_prompt(&mut explorer, "b");
+        assert_eq!(names(&explorer), ["b.pdf"]);
+
+        explorer.prompt_for_copy_file(KeyCode::Char('c'));
+        answer_prompt(&mut explorer, "c");
+        assert_eq!(names(&explorer), ["b.pdf", "c.pdf"]);
+    }
+
+    #[test]
+    fn directories_come_first_in_their_sorted_order() {
+        let (_dir, mut explorer) = explorer(&["b/", "a.txt", "d/", "c.txt"]);
+        assert_eq!(names(&explorer), ["b", "d", "a.txt", "c.txt"]);
+
+        explorer.toggle_dirs_first(KeyCode::Char('D'));
+        assert_eq!(names(&explorer), ["a.txt", "b", "c.txt", "d"]);
+    }
+
+    #[test]
+    fn moving_a_file_to_its_own_path_does_nothing() {
+        let (dir, mut explorer) = explorer(&["a.txt", "sub/"]);
+        let original = dir.path().join("a.txt");
+
+        for target in [
+            original.display().to_string(),
+            dir.path().join("sub/../a.txt").display().to_string(),
+        ] {
+            explorer
+                .apply_task(ExplorerTask::MoveFile(original.clone(), target))
+                .unwrap();
+            assert!(!explorer.modal.is_open());
+        }
+        assert_eq!(fs::read_to_string(&original).unwrap(), "a.txt");
+    }
+
+    #[test]
+    fn a_chosen_sort_survives_navigation() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt"]);
+        explorer.apply_task(ExplorerTask::Sort(3)).unwrap();
+
+        explorer.set_path(dir.path().join("sub")).unwrap();
+        assert_eq!(explorer.sort_name(), "Extension");
+        explorer.go_back(KeyCode::Esc);
+        assert_eq!(explorer.sort_name(), "Extension");
+    }
+
+    #[test]
+    fn a_directory_sort_applies_only_inside_that_directory() {
+        let (dir, mut explorer) = explorer(&["sub/a.txt"]);
+        fs::write(dir.path().join("sub/.rfm.toml"), "default_sort = \"size\"").unwrap();
+        explorer.apply_task(ExplorerTask::Sort(3)).unwrap();
+
+        explorer.set_path(dir.path().join("sub")).unwrap();
+        assert_eq!(explorer.sort_name(), "Size");
+        explorer.set_path(dir.path().to_path_buf()).unwrap();
+        assert_eq!(explorer.sort_name(), "Extension");
+    }
+
+    #[test]
+    fn pasting_is_refused_in_read_only_mode() {
+        let config = Config {
+            read_only: true,
+            ..Config::default()
+        };
+        let (dir, mut explorer) = explorer_with(&["a.txt", "sub/"], &config);
+        explorer.file_clipboard = Some((dir.path().join("a.txt"), ClipboardOp::Cut));
+        explorer.set_path(dir.path().join("sub")).unwrap();
+
+        explorer.paste_file(KeyCode::Char('p'));
+
+        assert!(explorer.modal.is_open());
+        assert!(dir.path().join("a.txt").is_file());
+        assert!(!dir.path().join("sub/a.txt").exists());
+        assert!(explorer.file_clipboard.is_some());
+    }
+}
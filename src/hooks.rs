@@ -0,0 +1,143 @@
+use std::path::Path;
+use std::process::Command as ProcessCommand;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::command::{Command, CommandHandler};
+
+#[derive(Clone, Deserialize)]
+pub struct HookConfig {
+    pub id: String,
+    pub name: String,
+    pub key: String,
+    pub command: String,
+    #[serde(default)]
+    pub interactive: bool,
+}
+
+pub struct Hook {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub key: String,
+    pub command: String,
+    pub interactive: bool,
+}
+
+static HOOKS: OnceLock<Vec<Hook>> = OnceLock::new();
+
+pub fn init_hooks(configs: &[HookConfig]) {
+    let hooks = configs
+        .iter()
+        .map(|config| Hook {
+            id: Box::leak(format!("hook.{}", config.id).into_boxed_str()),
+            name: Box::leak(config.name.clone().into_boxed_str()),
+            key: config.key.clone(),
+            command: config.command.clone(),
+            interactive: config.interactive,
+        })
+        .collect();
+    let _ = HOOKS.set(hooks);
+}
+
+pub fn get_hooks() -> &'static [Hook] {
+    HOOKS.get_or_init(Vec::new)
+}
+
+// Matches key sequences against the bindings of the hooks.
+pub struct HookKeys {
+    hooks: &'static [Hook],
+    triggered: Option<&'static Hook>,
+}
+
+impl HookKeys {
+    pub fn new(hooks: &'static [Hook]) -> Self {
+        Self {
+            hooks,
+            triggered: None,
+        }
+    }
+
+    pub fn take_triggered(&mut self) -> Option<&'static Hook> {
+        self.triggered.take()
+    }
+}
+
+impl CommandHandler for HookKeys {
+    fn get_name(&self) -> &'static str {
+        "hook"
+    }
+
+    fn get_commands(&self) -> &'static [Command<Self>] {
+        &[]
+    }
+
+    fn run_unlisted_command(&mut self, command_id: &'static str) -> bool {
+        self.triggered = self.hooks.iter().find(|hook| hook.id == command_id);
+        self.triggered.is_some()
+    }
+}
+
+impl Hook {
+    pub fn process(&self, file: &Path) -> ProcessCommand {
+        file_command(&self.command, file)
+    }
+}
+
+pub fn file_command(command: &str, file: &Path) -> ProcessCommand {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let command = command
+        .replace("{file}", &shell_quote(&file.display().to_string()))
+        .replace("{dir}", &shell_quote(&dir.display().to_string()));
+
+    shell_command(&command, dir)
+}
+
+pub fn shell_command(command: &str, dir: &Path) -> ProcessCommand {
+    let mut process = if cfg!(windows) {
+        let mut process = ProcessCommand::new("cmd");
+        process.arg("/C");
+        process
+    } else {
+        let mut process = ProcessCommand::new("sh");
+        process.arg("-c");
+        process
+    };
+    process.arg(command).current_dir(dir);
+    process
+}
+
+fn shell_quote(text: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", text)
+    } else {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bound_hooks_are_triggered_by_id() {
+        let hooks = vec![Hook {
+            id: "hook.compress",
+            name: "Compress",
+            key: String::from("z c"),
+            command: String::from("true"),
+            interactive: false,
+        }];
+        let mut keys = HookKeys::new(hooks.leak());
+
+        assert!(!keys.run_unlisted_command("hook.missing"));
+        assert!(keys.take_triggered().is_none());
+
+        assert!(keys.run_unlisted_command("hook.compress"));
+        assert_eq!(
+            keys.take_triggered().map(|hook| hook.name),
+            Some("Compress")
+        );
+        assert!(keys.take_triggered().is_none());
+    }
+}
@@ -1,6 +1,9 @@
-use std::{cell::RefCell, time::SystemTime};
+use std::{
+    cell::RefCell,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{
     layout::Rect,
     style::{Style, Stylize},
@@ -10,7 +13,8 @@ use ratatui::{
 };
 
 use crate::{
-    binding::{get_bindings, Binding},
+    binding::{get_bindings, Binding, Key},
+    config::{Config, LegendMode},
     window::Drawable,
 };
 
@@ -24,16 +28,22 @@ struct AnimationData {
 pub struct Legend {
     command_bindings_string: String,
     anim: RefCell<AnimationData>,
+    step: Duration,
+    mode: LegendMode,
 }
 
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 struct CommandBinding<'a> {
     command: &'a (&'static str, &'static str),
-    binding: &'a Binding,
+    bindings: Vec<&'a Binding>,
 }
 
 impl Legend {
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
         Legend {
+            step: Duration::from_millis(config.legend_step_ms),
+            mode: config.legend_mode,
             command_bindings_string: String::new(),
             anim: RefCell::new(AnimationData {
                 time: SystemTime::now(),
@@ -48,19 +58,32 @@ impl Legend {
 
         let command_bindings: Vec<CommandBinding> = commands
             .iter()
-            .map(|command| {
-                let binding = bindings
+            .map(|command| CommandBinding {
+                command,
+                bindings: bindings
                     .iter()
-                    .find(|binding| binding.command_id == command.0)
-                    .unwrap();
-                CommandBinding { command, binding }
+                    .filter(|binding| binding.command_id == command.0)
+                    .collect(),
             })
+            .filter(|cb| !cb.bindings.is_empty())
             .collect();
 
         let string_vec: Vec<String> = command_bindings
             .iter()
             .map(|cb| {
-                let key_str = &keycode_to_string(cb.binding.key_code);
+                let key_str = cb
+                    .bindings
+                    .iter()
+                    .map(|binding| {
+                        binding
+                            .keys
+                            .iter()
+                            .map(|key| key_to_string(*key))
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    })
+                    .collect::<Vec<String>>()
+                    .join("/");
                 let command_str = cb.command.1.to_string();
 
                 format!("[{key_str}] {command_str}")
@@ -72,12 +95,24 @@ impl Legend {
         self.command_bindings_string.push(' ');
     }
 
+    // Advances once per legend scroll step.
+    pub fn spinner_frame(&self) -> &'static str {
+        let elapsed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let step = self.step.as_millis().max(1);
+        SPINNER_FRAMES[(elapsed.as_millis() / step) as usize % SPINNER_FRAMES.len()]
+    }
+
     fn calc_anim_data(&self, area: Rect) {
         let x_margin = 2u16;
 
-        let legend_width = self.command_bindings_string.len() as u16 + x_margin;
+        let legend_width = u16::try_from(self.command_bindings_string.chars().count())
+            .unwrap_or(u16::MAX)
+            .saturating_add(x_margin);
+        let max_scroll = legend_width.saturating_sub(area.width);
 
-        if legend_width <= area.width {
+        if max_scroll == 0 || self.mode == LegendMode::None {
             self.anim.replace(AnimationData {
                 time: SystemTime::now(),
                 dir: false,
@@ -87,28 +122,34 @@ impl Legend {
         }
         let anim = self.anim.clone().into_inner();
         let now = SystemTime::now();
-        let scroll_pos = anim.scroll_pos;
+        // The area may have shrunk or grown since the last step.
+        let scroll_pos = anim.scroll_pos.min(max_scroll);
+        self.anim.borrow_mut().scroll_pos = scroll_pos;
 
         if let Ok(diff) = now.duration_since(anim.time) {
-            if diff.as_millis() < 250 {
+            if diff < self.step {
                 return;
             }
         } else {
             return;
         }
 
-        let (new_dir, new_scroll_pos) = if !anim.dir {
-            if scroll_pos + area.width < legend_width {
+        let (new_dir, new_scroll_pos) = if self.mode == LegendMode::Loop {
+            if scroll_pos < max_scroll {
                 (false, scroll_pos + 1)
             } else {
-                (true, scroll_pos - 1)
+                (false, 0)
             }
-        } else {
-            if scroll_pos == 0 {
+        } else if !anim.dir {
+            if scroll_pos < max_scroll {
                 (false, scroll_pos + 1)
             } else {
-                (true, scroll_pos - 1)
+                (true, scroll_pos.saturating_sub(1))
             }
+        } else if scroll_pos == 0 {
+            (false, 1)
+        } else {
+            (true, scroll_pos - 1)
         };
 
         self.anim.replace(AnimationData {
@@ -134,6 +175,21 @@ impl Drawable for Legend {
     }
 }
 
+fn key_to_string(key: Key) -> String {
+    let mut text = String::new();
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        text.push_str("Ctrl-");
+    }
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        text.push_str("Alt-");
+    }
+    if key.modifiers.contains(KeyModifiers::SHIFT) {
+        text.push_str("Shift-");
+    }
+    text.push_str(&keycode_to_string(key.code));
+    text
+}
+
 fn keycode_to_string(keycode: KeyCode) -> String {
     match keycode {
         KeyCode::Backspace => "Backspace".to_string(),
@@ -151,6 +207,7 @@ fn keycode_to_string(keycode: KeyCode) -> String {
         KeyCode::Delete => "Delete".to_string(),
         KeyCode::Insert => "Insert".to_string(),
         KeyCode::F(n) => format!("F{}", n),
+        KeyCode::Char(' ') => "Space".to_string(),
         KeyCode::Char(c) => c.to_string(),
         KeyCode::Esc => "Escape".to_string(),
         KeyCode::Null => "Null".to_string(),
@@ -163,3 +220,78 @@ fn keycode_to_string(keycode: KeyCode) -> String {
         _ => "Unknown key".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legend(text: &str, mode: LegendMode) -> Legend {
+        let mut legend = Legend::new(&Config {
+            legend_mode: mode,
+            ..Config::default()
+        });
+        legend.command_bindings_string = text.to_string();
+        legend
+    }
+
+    // Runs one animation step as if a full step had elapsed.
+    fn step(legend: &Legend, width: u16) -> u16 {
+        legend.anim.borrow_mut().time = SystemTime::now() - legend.step;
+        legend.calc_anim_data(Rect::new(0, 0, width, 3));
+        legend.anim.borrow().scroll_pos
+    }
+
+    #[test]
+    fn a_legend_that_just_fits_does_not_scroll() {
+        let legend = legend("0123456789", LegendMode::Bounce);
+
+        assert_eq!(step(&legend, 12), 0);
+        assert_eq!(step(&legend, 12), 0);
+        assert_eq!(step(&legend, 40), 0);
+    }
+
+    #[test]
+    fn a_legend_bounces_between_its_ends_on_a_narrow_terminal() {
+        let legend = legend("0123456789", LegendMode::Bounce);
+
+        let positions: Vec<u16> = (0..6).map(|_| step(&legend, 10)).collect();
+        assert_eq!(positions, [1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn a_legend_loops_back_to_the_start() {
+        let legend = legend("0123456789", LegendMode::Loop);
+
+        let positions: Vec<u16> = (0..4).map(|_| step(&legend, 10)).collect();
+        assert_eq!(positions, [1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn a_terminal_narrower_than_the_margin_does_not_underflow() {
+        let legend = legend("0123456789", LegendMode::Bounce);
+
+        for width in [0, 1, 2] {
+            assert!(step(&legend, width) <= 12);
+        }
+    }
+
+    #[test]
+    fn growing_the_terminal_mid_scroll_clamps_the_position() {
+        let legend = legend("0123456789", LegendMode::Bounce);
+        for _ in 0..2 {
+            step(&legend, 10);
+        }
+
+        assert_eq!(step(&legend, 11), 0);
+        assert_eq!(step(&legend, 12), 0);
+    }
+
+    #[test]
+    fn modified_keys_are_shown_with_their_modifiers() {
+        let ctrl_q = Key::new(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        assert_eq!(key_to_string(ctrl_q), "Ctrl-q");
+        let alt_left = Key::new(KeyCode::Left, KeyModifiers::ALT);
+        assert_eq!(key_to_string(alt_left), "Alt-Left Arrow");
+        assert_eq!(key_to_string(KeyCode::Char('Q').into()), "Q");
+    }
+}
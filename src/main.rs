@@ -1,18 +1,26 @@
 mod app;
+mod background;
 mod binding;
+mod clipboard;
 mod command;
+mod config;
 mod editor;
+mod editor_tabs;
 mod file_explorer;
+mod hooks;
 mod legend;
 mod modal;
 mod modal_variants;
+mod paths;
+mod quick_look;
+mod session;
 mod sort_entries;
 mod text_editor;
 mod window;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use app::App;
-use command::InputHandler;
+use config::Config;
 use crossterm::{
     event::{self, KeyEventKind},
     execute,
@@ -20,45 +28,118 @@ use crossterm::{
 };
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
+use std::fs::{self, OpenOptions};
 use std::io;
+use std::path::PathBuf;
 
-fn init() -> Result<Terminal<CrosstermBackend<io::Stdout>>, io::Error> {
+fn init(alt_screen: bool) -> Result<Terminal<CrosstermBackend<io::Stdout>>, io::Error> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    if alt_screen {
+        execute!(stdout, EnterAlternateScreen)?;
+    }
     let backend = CrosstermBackend::new(stdout);
-    let terminal = Terminal::new(backend)?;
+    let mut terminal = Terminal::new(backend)?;
+    if !alt_screen {
+        terminal.clear()?;
+    }
 
     Ok(terminal)
 }
 
-fn exit(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), io::Error> {
+fn exit(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    alt_screen: bool,
+) -> Result<(), io::Error> {
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    if alt_screen {
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    } else {
+        // Leave the last frame in the scrollback and put the prompt below it.
+        let height = terminal.size()?.height;
+        terminal.set_cursor(0, height.saturating_sub(1))?;
+        println!();
+    }
     terminal.show_cursor()?;
     Ok(())
 }
 
+fn log_path(args: &[String]) -> Option<PathBuf> {
+    if args.iter().any(|arg| arg == "--no-log") {
+        return None;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--log-file") {
+        return args.get(index + 1).map(PathBuf::from);
+    }
+    Some(paths::log_file())
+}
+
+fn init_logger() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = log_path(&args) else {
+        return Ok(());
+    };
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("could not create log directory {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("log file {} is not writable", path.display()))?;
+    env_logger::Builder::from_env(env_logger::Env::default().filter_or("RFM_LOG", "info"))
+        .target(env_logger::Target::Pipe(Box::new(file)))
+        .init();
+    Ok(())
+}
+
 fn main() -> Result<()> {
-    let mut terminal = init().unwrap();
+    init_logger()?;
+    let dirs = paths::paths();
+    log::info!(
+        "config: {}, state: {}, cache: {}",
+        dirs.config.display(),
+        dirs.state.display(),
+        dirs.cache.display()
+    );
+    let config = Config::load();
+    hooks::init_hooks(&config.hooks);
+    binding::init_bindings(&config.bindings);
+    let alt_screen = !std::env::args().any(|arg| arg == "--no-alt-screen");
+    let mut terminal = init(alt_screen).unwrap();
 
-    let mut app = App::new()?;
+    let mut app = App::new(config)?;
 
     loop {
+        app.update();
         let _ = terminal.draw(|f| app.draw(f));
 
         if event::poll(std::time::Duration::from_millis(16))? {
             if let event::Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
-                    app.handle_input(key.code);
+                    app.handle_key_event(key);
                 }
             }
         }
 
+        if let Some(mut process) = app.external_process.take() {
+            exit(&mut terminal, alt_screen)?;
+            let status = process.status();
+            terminal = init(alt_screen)?;
+            terminal.clear()?;
+            app.on_external_process_exit(status);
+        }
+
         if app.should_stop {
             break;
         }
     }
 
-    Ok(exit(&mut terminal)?)
+    app.save_session();
+
+    Ok(exit(&mut terminal, alt_screen)?)
 }
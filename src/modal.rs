@@ -1,14 +1,12 @@
 use crossterm::event::KeyCode;
-use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    Frame,
-};
+use ratatui::{layout::Rect, widgets::Clear, Frame};
 
 use crate::window::Drawable;
 
 pub trait ModalVariant {
     fn handle_input(&mut self, state: &mut ModalState, key_code: KeyCode);
     fn draw(&self, f: &mut Frame, area: Rect);
+    fn size(&self, area: Rect) -> (u16, u16);
 }
 
 pub struct Modal {
@@ -47,24 +45,55 @@ impl Drawable for Modal {
             return;
         }
 
-        let tmp = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(20),
-                Constraint::Percentage(60),
-                Constraint::Percentage(20),
-            ])
-            .split(area);
-
-        let popup_wrapper = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(10),
-                Constraint::Percentage(80),
-                Constraint::Percentage(10),
-            ])
-            .split(tmp[1])[1];
+        let (width, height) = self.variant_trait.size(area);
+        let popup_wrapper = centered_rect(area, width, height);
 
+        f.render_widget(Clear, popup_wrapper);
         self.variant_trait.draw(f, popup_wrapper);
     }
 }
+
+const MIN_POPUP_WIDTH: u16 = 30;
+const POPUP_PADDING: u16 = 6;
+
+pub fn popup_width(area: Rect, content_width: usize) -> u16 {
+    let max_width = (area.width.saturating_mul(9) / 10).max(MIN_POPUP_WIDTH);
+    (content_width as u16)
+        .saturating_add(POPUP_PADDING)
+        .clamp(MIN_POPUP_WIDTH, max_width)
+        .min(area.width)
+}
+
+pub fn wrapped_line_count(text: &str, width: u16) -> u16 {
+    let width = width.max(1) as usize;
+    let mut count = 0;
+    for line in text.lines() {
+        let mut line_count = 1;
+        let mut current = 0;
+        for word in line.split_whitespace() {
+            let word_width = word.chars().count();
+            if current > 0 && current + 1 + word_width <= width {
+                current += 1 + word_width;
+            } else {
+                if current > 0 {
+                    line_count += 1;
+                }
+                line_count += word_width.saturating_sub(1) / width;
+                current = (word_width - 1) % width + 1;
+            }
+        }
+        count += line_count;
+    }
+    count.max(1) as u16
+}
+
+pub fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
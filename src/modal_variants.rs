@@ -1,62 +1,184 @@
+use std::cell::Cell;
+
 use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    text::Text,
+    style::{Color, Style},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
     Frame,
 };
 
-use crate::modal::{ModalState, ModalVariant};
+use crate::modal::{popup_width, wrapped_line_count, ModalState, ModalVariant};
 
 pub struct InfoVariant {
     message: String,
+    scroll: Cell<u16>,
 }
 
 impl InfoVariant {
     pub fn new(message: String) -> Self {
-        Self { message }
+        Self {
+            message,
+            scroll: Cell::new(0),
+        }
     }
 }
 
 impl ModalVariant for InfoVariant {
     fn handle_input(&mut self, state: &mut ModalState, key_code: KeyCode) {
-        match key_code {
-            KeyCode::Char('y') => {
-                state.is_open = false;
-            }
-            _ => {}
+        if matches!(
+            key_code,
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char(' ') | KeyCode::Char('y')
+        ) {
+            state.is_open = false;
         }
+        scroll_message(&self.scroll, key_code);
     }
 
     fn draw(&self, f: &mut Frame, area: Rect) {
-        draw_with_legend(&self.message, f, area, vec!["Ok [y]".to_string()]);
+        draw_with_legend(&self.message, &self.scroll, f, area, info_legend());
+    }
+
+    fn size(&self, area: Rect) -> (u16, u16) {
+        size_with_legend(&self.message, area, &info_legend())
     }
 }
 
+fn info_legend() -> Vec<String> {
+    vec!["Ok [Enter]".to_string()]
+}
+
 pub struct QuestionVariant {
     message: String,
     answer: String,
+    cursor: usize,
+    selection: Option<(usize, usize)>,
     on_confirm: ModalCallback<String>,
 }
 
 impl QuestionVariant {
     pub fn new(message: String, answer: String, on_confirm: ModalCallback<String>) -> Self {
+        let answer_len = answer.chars().count();
+        let selection = if answer_len > 0 {
+            Some((0, answer_len))
+        } else {
+            None
+        };
         Self {
             message,
             answer,
+            cursor: answer_len,
+            selection,
             on_confirm,
         }
     }
+
+    pub fn with_selection(mut self, start: usize, end: usize) -> Self {
+        let end = end.min(self.answer_len());
+        self.selection = if start < end {
+            Some((start, end))
+        } else {
+            None
+        };
+        self.cursor = end;
+        self
+    }
+
+    fn answer_len(&self) -> usize {
+        self.answer.chars().count()
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.answer
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.answer.len(), |(index, _)| index)
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        if let Some((start, end)) = self.selection.take() {
+            let range = self.byte_index(start)..self.byte_index(end);
+            self.answer.replace_range(range, "");
+            self.cursor = start;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            self.cursor -= 1;
+            self.answer.remove(self.byte_index(self.cursor));
+        }
+    }
+
+    fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.answer_len() {
+            self.answer.remove(self.byte_index(self.cursor));
+        }
+    }
+
+    fn draw_answer(&self) -> Line<'_> {
+        let cursor_style = Style::default().fg(Color::Black).bg(Color::White);
+        let selection_style = Style::default().bg(Color::Blue);
+
+        let mut spans: Vec<Span> = self
+            .answer
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let style = match self.selection {
+                    _ if i == self.cursor => cursor_style,
+                    Some((start, end)) if i >= start && i < end => selection_style,
+                    _ => Style::default(),
+                };
+                Span::styled(c.to_string(), style)
+            })
+            .collect();
+        if self.cursor == self.answer_len() {
+            spans.push(Span::styled(" ", cursor_style));
+        }
+        Line::from(spans)
+    }
 }
 
 impl ModalVariant for QuestionVariant {
     fn handle_input(&mut self, state: &mut ModalState, key_code: KeyCode) {
         match key_code {
-            KeyCode::Backspace => {
-                self.answer.pop();
-            }
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
             KeyCode::Char(c) => {
-                self.answer.push(c);
+                self.delete_selection();
+                let index = self.byte_index(self.cursor);
+                self.answer.insert(index, c);
+                self.cursor += 1;
+            }
+            KeyCode::Left => {
+                self.cursor = match self.selection.take() {
+                    Some((start, _)) => start,
+                    None => self.cursor.saturating_sub(1),
+                };
+            }
+            KeyCode::Right => {
+                self.cursor = match self.selection.take() {
+                    Some((_, end)) => end,
+                    None => (self.cursor + 1).min(self.answer_len()),
+                };
+            }
+            KeyCode::Tab => {
+                let start = self.selection.map_or(self.cursor, |(start, _)| start);
+                self.cursor = self.answer_len();
+                self.selection = Some((start, self.cursor)).filter(|(start, end)| start < end);
+            }
+            KeyCode::Home => {
+                self.selection = None;
+                self.cursor = 0;
+            }
+            KeyCode::End => {
+                self.selection = None;
+                self.cursor = self.answer_len();
             }
             KeyCode::Enter => {
                 state.is_open = false;
@@ -72,19 +194,20 @@ impl ModalVariant for QuestionVariant {
     }
 
     fn draw(&self, f: &mut Frame, area: Rect) {
+        let block = Block::new().borders(Borders::all());
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Percentage(10),
-                Constraint::Percentage(30),
-                Constraint::Percentage(30),
-                Constraint::Percentage(20),
-                Constraint::Percentage(10),
+                Constraint::Length(1),
+                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Length(3),
+                Constraint::Length(1),
             ])
-            .split(area);
+            .split(block.inner(area));
 
         let question_wrapper = chunks[1];
-        let answer_wrapper = chunks[2];
+        let answer_wrapper = chunks[3];
 
         let question_text = Paragraph::new(self.message.as_str())
             .block(Block::default())
@@ -100,27 +223,41 @@ impl ModalVariant for QuestionVariant {
             ])
             .split(answer_wrapper);
 
-        let answer_text = Paragraph::new(self.answer.as_str())
+        let answer_text = Paragraph::new(self.draw_answer())
             .block(Block::default().borders(Borders::ALL))
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: true });
 
-        draw_modal_legend(
-            vec![String::from("Ok [Enter]"), String::from("Cancel [Esc]")],
-            chunks[3],
-            f,
-        );
+        draw_modal_legend(question_legend(), chunks[4], f);
 
-        f.render_widget(Block::new().borders(Borders::all()), area);
+        f.render_widget(block, area);
         f.render_widget(question_text, question_wrapper);
         f.render_widget(answer_text, answers_chunks[1]);
     }
+
+    fn size(&self, area: Rect) -> (u16, u16) {
+        let content_width = self
+            .message
+            .lines()
+            .map(|line| line.chars().count())
+            .chain([self.answer_len() + 5, legend_width(&question_legend())])
+            .max()
+            .unwrap_or(0);
+        let width = popup_width(area, content_width);
+        let message_height = wrapped_line_count(&self.message, width.saturating_sub(2));
+        (width, message_height + 8)
+    }
+}
+
+fn question_legend() -> Vec<String> {
+    vec![String::from("Ok [Enter]"), String::from("Cancel [Esc]")]
 }
 
 type ModalCallback<T = ()> = Box<dyn Fn(T)>;
 
 pub struct ConfirmationVariant {
     message: String,
+    scroll: Cell<u16>,
     on_confirm: ModalCallback,
 }
 
@@ -128,6 +265,7 @@ impl ConfirmationVariant {
     pub fn new(message: String, on_confirm: ModalCallback) -> Self {
         Self {
             message,
+            scroll: Cell::new(0),
             on_confirm,
         }
     }
@@ -143,18 +281,21 @@ impl ModalVariant for ConfirmationVariant {
             KeyCode::Char('n') => {
                 state.is_open = false;
             }
-            _ => {}
+            _ => scroll_message(&self.scroll, key_code),
         }
     }
 
     fn draw(&self, f: &mut Frame, area: Rect) {
-        draw_with_legend(
-            &self.message,
-            f,
-            area,
-            vec!["Yes [y]".to_string(), "No [n]".to_string()],
-        );
+        draw_with_legend(&self.message, &self.scroll, f, area, confirmation_legend());
     }
+
+    fn size(&self, area: Rect) -> (u16, u16) {
+        size_with_legend(&self.message, area, &confirmation_legend())
+    }
+}
+
+fn confirmation_legend() -> Vec<String> {
+    vec!["Yes [y]".to_string(), "No [n]".to_string()]
 }
 
 pub struct OptionsVariant {
@@ -194,68 +335,132 @@ impl ModalVariant for OptionsVariant {
     }
 
     fn draw(&self, f: &mut Frame, area: Rect) {
+        let block = Block::new().borders(Borders::all());
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
+                Constraint::Length(1),
                 Constraint::Fill(1),
-                Constraint::Fill(2),
-                Constraint::Fill(2),
-                Constraint::Fill(2),
-                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Length(self.options.len() as u16),
+                Constraint::Length(1),
+                Constraint::Length(1),
             ])
-            .split(area);
+            .split(block.inner(area));
 
         let question_wrapper = chunks[1];
-        let options_wrapper = chunks[2];
+        let options_wrapper = chunks[3];
 
         let question_text = Paragraph::new(self.message.as_str())
             .block(Block::default())
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: true });
 
-        let option_texts = self
-            .options
+        let list = List::new(
+            self.option_texts()
+                .map(|text| ListItem::new(Text::from(text).alignment(Alignment::Center))),
+        );
+
+        draw_modal_legend(options_legend(), chunks[5], f);
+
+        f.render_widget(block, area);
+        f.render_widget(question_text, question_wrapper);
+        f.render_widget(list, options_wrapper);
+    }
+
+    fn size(&self, area: Rect) -> (u16, u16) {
+        let content_width = self
+            .message
+            .lines()
+            .map(|line| line.chars().count())
+            .chain(self.option_texts().map(|text| text.chars().count()))
+            .chain([legend_width(&options_legend())])
+            .max()
+            .unwrap_or(0);
+        let width = popup_width(area, content_width);
+        let message_height = wrapped_line_count(&self.message, width.saturating_sub(2));
+        (width, message_height + self.options.len() as u16 + 6)
+    }
+}
+
+impl OptionsVariant {
+    fn option_texts(&self) -> impl Iterator<Item = String> + '_ {
+        self.options
             .iter()
             .enumerate()
             .map(|(i, option)| format!("[{}]. {}", i + 1, option))
-            .map(|text| ListItem::new(Text::from(text).alignment(Alignment::Center)));
-
-        let list = List::new(option_texts);
+    }
+}
 
-        draw_modal_legend(vec![String::from("Cancel [Esc]")], chunks[3], f);
+fn options_legend() -> Vec<String> {
+    vec![String::from("Cancel [Esc]")]
+}
 
-        f.render_widget(Block::new().borders(Borders::all()), area);
-        f.render_widget(question_text, question_wrapper);
-        f.render_widget(list, options_wrapper);
+fn scroll_message(scroll: &Cell<u16>, key_code: KeyCode) {
+    match key_code {
+        KeyCode::Char('j') | KeyCode::Down => scroll.set(scroll.get().saturating_add(1)),
+        KeyCode::Char('k') | KeyCode::Up => scroll.set(scroll.get().saturating_sub(1)),
+        _ => {}
     }
 }
 
-fn draw_with_legend(message: &String, f: &mut Frame, popup_wrapper: Rect, legend: Vec<String>) {
+fn draw_with_legend(
+    message: &str,
+    scroll: &Cell<u16>,
+    f: &mut Frame,
+    popup_wrapper: Rect,
+    legend: Vec<String>,
+) {
+    let block = Block::new().borders(Borders::all());
     let v_segments = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(10),
-            Constraint::Percentage(30),
-            Constraint::Percentage(10),
-            Constraint::Percentage(30),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
         ])
-        .split(popup_wrapper);
+        .split(block.inner(popup_wrapper));
 
     let question_wrapper = v_segments[1];
 
-    let spacer_block = Block::new();
-    let spacer = v_segments[2];
-
-    let question_block = Block::new();
-    let question = Paragraph::new(message.as_str())
-        .centered()
-        .block(question_block);
+    // Multi-line messages are lists or reports, which read better left-aligned.
+    let multi_line = message.contains('\n');
+    let line_count = wrapped_line_count(message, question_wrapper.width);
+    scroll.set(
+        scroll
+            .get()
+            .min(line_count.saturating_sub(question_wrapper.height)),
+    );
+    let question = Paragraph::new(message)
+        .alignment(if multi_line {
+            Alignment::Left
+        } else {
+            Alignment::Center
+        })
+        .wrap(Wrap { trim: !multi_line })
+        .scroll((scroll.get(), 0));
 
     draw_modal_legend(legend, v_segments[3], f);
 
-    f.render_widget(Block::new().borders(Borders::all()), popup_wrapper);
+    f.render_widget(block, popup_wrapper);
     f.render_widget(question, question_wrapper);
-    f.render_widget(spacer_block, spacer);
+}
+
+fn size_with_legend(message: &str, area: Rect, legend: &[String]) -> (u16, u16) {
+    let content_width = message
+        .lines()
+        .map(|line| line.chars().count())
+        .chain([legend_width(legend)])
+        .max()
+        .unwrap_or(0);
+    let width = popup_width(area, content_width);
+    let message_height = wrapped_line_count(message, width.saturating_sub(2));
+    (width, message_height + 5)
+}
+
+fn legend_width(legend: &[String]) -> usize {
+    legend.iter().map(|item| item.chars().count() + 4).sum()
 }
 
 fn draw_modal_legend(legend: Vec<String>, area: Rect, f: &mut Frame) {
@@ -270,3 +475,52 @@ fn draw_modal_legend(legend: Vec<String>, area: Rect, f: &mut Frame) {
         f.render_widget(paragraph, answer_segments[i]);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    fn render(variant: &dyn ModalVariant, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| variant.draw(f, f.size())).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn row(buffer: &Buffer, y: u16) -> String {
+        (0..buffer.area.width)
+            .map(|x| buffer.get(x, y).symbol())
+            .collect()
+    }
+
+    #[test]
+    fn multi_line_confirmations_are_left_aligned_one_name_per_row() {
+        let confirmation = ConfirmationVariant::new(
+            "Delete 3 files?\na.txt\nb.txt\n…and 1 more".to_string(),
+            Box::new(|_| {}),
+        );
+
+        let buffer = render(&confirmation, 30, 10);
+        assert!(row(&buffer, 2).starts_with("│Delete 3 files?"));
+        assert!(row(&buffer, 3).starts_with("│a.txt"));
+        assert!(row(&buffer, 4).starts_with("│b.txt"));
+        assert!(row(&buffer, 5).starts_with("│…and 1 more"));
+    }
+
+    #[test]
+    fn long_confirmations_scroll_with_j_and_k() {
+        let names: Vec<String> = (0..10).map(|i| format!("file{i}")).collect();
+        let mut confirmation = ConfirmationVariant::new(names.join("\n"), Box::new(|_| {}));
+        let mut state = ModalState { is_open: true };
+
+        render(&confirmation, 30, 8);
+        confirmation.handle_input(&mut state, KeyCode::Char('j'));
+        let buffer = render(&confirmation, 30, 8);
+        assert!(row(&buffer, 2).starts_with("│file1"));
+
+        confirmation.handle_input(&mut state, KeyCode::Char('k'));
+        let buffer = render(&confirmation, 30, 8);
+        assert!(row(&buffer, 2).starts_with("│file0"));
+        assert!(state.is_open);
+    }
+}
@@ -0,0 +1,43 @@
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const APP_DIR: &str = "rfm";
+
+pub struct Paths {
+    pub config: PathBuf,
+    pub state: PathBuf,
+    pub cache: PathBuf,
+}
+
+pub fn paths() -> &'static Paths {
+    static PATHS: OnceLock<Paths> = OnceLock::new();
+    PATHS.get_or_init(|| {
+        let config = dirs::config_dir().unwrap_or_else(env::temp_dir);
+        let state = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .unwrap_or_else(env::temp_dir);
+        let cache = dirs::cache_dir().unwrap_or_else(env::temp_dir);
+        Paths {
+            config: config.join(APP_DIR),
+            state: state.join(APP_DIR),
+            cache: cache.join(APP_DIR),
+        }
+    })
+}
+
+pub fn config_file() -> PathBuf {
+    paths().config.join("config.toml")
+}
+
+pub fn log_file() -> PathBuf {
+    paths().state.join("rfm.log")
+}
+
+pub fn session_file() -> PathBuf {
+    paths().state.join("session.toml")
+}
+
+pub fn templates_dir() -> PathBuf {
+    paths().config.join("templates")
+}
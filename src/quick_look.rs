@@ -0,0 +1,116 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Result;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::Rect,
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::window::Drawable;
+
+pub const MAX_PREVIEW_BYTES: u64 = 256 * 1024;
+const HEX_BYTES_PER_LINE: usize = 16;
+
+pub struct QuickLook {
+    title: String,
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+impl QuickLook {
+    pub fn open(path: &Path) -> Result<QuickLook> {
+        let lines = if path.is_dir() {
+            let mut names: Vec<String> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        } else {
+            let mut bytes = Vec::new();
+            File::open(path)?
+                .take(MAX_PREVIEW_BYTES)
+                .read_to_end(&mut bytes)?;
+            match std::str::from_utf8(&bytes) {
+                Ok(text) if !bytes.contains(&0) => text.lines().map(str::to_string).collect(),
+                _ => hex_dump(&bytes),
+            }
+        };
+
+        Ok(QuickLook {
+            title: path.display().to_string(),
+            lines,
+            scroll: 0,
+        })
+    }
+
+    // Returns false when the overlay should be closed.
+    pub fn handle_input(&mut self, key_code: KeyCode) -> bool {
+        match key_code {
+            KeyCode::Esc | KeyCode::Char(' ') | KeyCode::Char('q') => return false,
+            KeyCode::Char('j') | KeyCode::Down => self.scroll_by(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll_by(-1),
+            KeyCode::PageDown => self.scroll_by(20),
+            KeyCode::PageUp => self.scroll_by(-20),
+            _ => {}
+        }
+        true
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let max_scroll = self.lines.len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max_scroll);
+    }
+}
+
+impl Drawable for QuickLook {
+    fn draw(&self, f: &mut Frame, area: Rect) {
+        let visible_rows = area.height.saturating_sub(2) as usize;
+        let text = self
+            .lines
+            .iter()
+            .skip(self.scroll)
+            .take(visible_rows)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+        let block = Block::default()
+            .title(format!(" {} ", self.title))
+            .title_bottom(" Esc to close ")
+            .borders(Borders::ALL);
+
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(HEX_BYTES_PER_LINE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  {}",
+                i * HEX_BYTES_PER_LINE,
+                hex.join(" "),
+                ascii,
+                width = HEX_BYTES_PER_LINE * 3 - 1
+            )
+        })
+        .collect()
+}
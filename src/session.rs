@@ -0,0 +1,33 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub sort: String,
+    pub filter: String,
+    pub recent_files: Vec<PathBuf>,
+}
+
+impl Session {
+    pub fn load() -> Option<Session> {
+        let text = fs::read_to_string(paths::session_file()).ok()?;
+        toml::from_str(&text)
+            .inspect_err(|e| log::warn!("invalid session file: {}", e))
+            .ok()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = paths::session_file();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
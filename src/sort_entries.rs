@@ -4,15 +4,16 @@ use std::path::PathBuf;
 
 pub struct SortEntry {
     pub name: &'static str,
-    pub func: fn(&mut Vec<PathBuf>) -> Result<bool>,
+    pub func: fn(&mut [PathBuf]) -> Result<bool>,
+    pub descending: bool,
 }
 
-fn sort_by_name(entries: &mut Vec<PathBuf>) -> Result<bool> {
+fn sort_by_name(entries: &mut [PathBuf]) -> Result<bool> {
     entries.sort();
     Ok(true)
 }
 
-fn sort_by_size(entries: &mut Vec<PathBuf>) -> Result<bool> {
+fn sort_by_size(entries: &mut [PathBuf]) -> Result<bool> {
     entries.sort_by(|a, b| {
         let a_size = fs::metadata(a).unwrap().len();
         let b_size = fs::metadata(b).unwrap().len();
@@ -21,7 +22,7 @@ fn sort_by_size(entries: &mut Vec<PathBuf>) -> Result<bool> {
     Ok(true)
 }
 
-fn sort_by_modified_date(entries: &mut Vec<PathBuf>) -> Result<bool> {
+fn sort_by_modified_date(entries: &mut [PathBuf]) -> Result<bool> {
     entries.sort_by(|a, b| {
         let a_time = fs::metadata(a).unwrap().modified().unwrap();
         let b_time = fs::metadata(b).unwrap().modified().unwrap();
@@ -30,17 +31,35 @@ fn sort_by_modified_date(entries: &mut Vec<PathBuf>) -> Result<bool> {
     Ok(true)
 }
 
-pub const SORT_ENTRIES: [SortEntry; 3] = [
+fn sort_by_extension(entries: &mut [PathBuf]) -> Result<bool> {
+    entries.sort_by_cached_key(|entry| {
+        let extension = entry
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        (extension, entry.clone())
+    });
+    Ok(true)
+}
+
+pub const SORT_ENTRIES: [SortEntry; 4] = [
     SortEntry {
         name: "Name",
         func: sort_by_name,
+        descending: false,
     },
     SortEntry {
         name: "Size",
         func: sort_by_size,
+        descending: true,
     },
     SortEntry {
         name: "Modified Date",
         func: sort_by_modified_date,
+        descending: true,
+    },
+    SortEntry {
+        name: "Extension",
+        func: sort_by_extension,
+        descending: false,
     },
 ];
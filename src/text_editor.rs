@@ -1,10 +1,16 @@
 use std::{
+    cell::Cell,
+    collections::VecDeque,
     fs::{self},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+    time::SystemTime,
 };
 
 use anyhow::{Context, Result};
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
 use crossterm::event::KeyCode;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style, Stylize},
@@ -12,14 +18,43 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
+use unicode_width::UnicodeWidthChar;
 
 use crate::{
     as_command,
+    binding::Key,
     command::{Command, CommandHandler, InputHandler},
+    config::Config,
     editor::Editor,
+    modal::{centered_rect, Modal},
+    modal_variants::{ConfirmationVariant, InfoVariant, OptionsVariant, QuestionVariant},
+    quick_look::{hex_dump, MAX_PREVIEW_BYTES},
     window::{Drawable, Focusable},
 };
 
+const BINARY_SNIFF_LEN: usize = 8192;
+const MAX_REMEMBERED_POSITIONS: usize = 64;
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+const DEFAULT_COMMENT_PREFIX: &str = "#";
+const COMMENT_PREFIXES: [(&str, &str); 16] = [
+    ("rs", "//"),
+    ("js", "//"),
+    ("ts", "//"),
+    ("c", "//"),
+    ("h", "//"),
+    ("cpp", "//"),
+    ("java", "//"),
+    ("go", "//"),
+    ("py", "#"),
+    ("sh", "#"),
+    ("toml", "#"),
+    ("yaml", "#"),
+    ("yml", "#"),
+    ("sql", "--"),
+    ("lua", "--"),
+    ("hs", "--"),
+];
+
 #[derive(Copy, Clone)]
 struct CursorPosition {
     line: usize,
@@ -32,6 +67,11 @@ impl CursorPosition {
     }
 }
 
+struct Snapshot {
+    lines: Vec<String>,
+    cursor_position: CursorPosition,
+}
+
 #[derive(PartialEq)]
 enum Mode {
     View,
@@ -45,29 +85,106 @@ pub struct TextEditor {
     lines: Vec<String>,
     mode: Mode,
     file_saved: bool,
-    pub modal_open: bool,
+    disk_stamp: Option<DiskStamp>,
+    remembered_positions: VecDeque<(PathBuf, CursorPosition)>,
+    soft_wrap: bool,
+    line_numbers: bool,
+    search_query: String,
+    crlf: bool,
+    lossy: bool,
+    encoding: &'static Encoding,
+    forced_encoding: Option<&'static Encoding>,
+    auto_pair: bool,
+    tab_width: usize,
+    indent_with_tabs: bool,
+    read_only: bool,
+    binary: bool,
+    scroll_off: usize,
+    scroll_top: Cell<usize>,
+    modal_open: bool,
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    undo_limit: usize,
+    coalesce_inserts: bool,
+
+    modal: Modal,
+    sender: Sender<EditorTask>,
+    receiver: Receiver<EditorTask>,
+}
+
+pub enum EditorTask {
+    GotoLine(String),
+    ResolveDiskChange(usize),
+    Reload,
+    SetEncoding(String),
+    SaveAsUtf8,
+    Search(String),
+    ReplaceSearch(String),
+    Replace(String, String),
+}
+
+#[derive(PartialEq, Clone, Copy)]
+struct DiskStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl DiskStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(DiskStamp {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
 }
 
 impl TextEditor {
-    pub fn new() -> Self {
-        let editor = TextEditor {
+    pub fn new(config: &Config) -> Self {
+        let (sender, receiver) = channel();
+
+        let mut modal = Modal::new(Box::new(InfoVariant::new(String::new())));
+        modal.close();
+        TextEditor {
             cursor_position: CursorPosition { line: 0, char: 0 },
             is_focused: false,
             file: PathBuf::new(),
             lines: Vec::new(),
             mode: Mode::View,
             file_saved: true,
+            disk_stamp: None,
+            remembered_positions: VecDeque::new(),
+            soft_wrap: false,
+            line_numbers: config.line_numbers,
+            search_query: String::new(),
+            crlf: false,
+            lossy: false,
+            encoding: UTF_8,
+            forced_encoding: None,
+            auto_pair: config.auto_pair,
+            tab_width: config.tab_width.max(1),
+            indent_with_tabs: config.indent_with_tabs,
+            read_only: config.read_only,
+            binary: false,
+            scroll_off: config.scroll_off,
+            scroll_top: Cell::new(0),
             modal_open: false,
-        };
-        editor
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            undo_limit: config.undo_limit,
+            coalesce_inserts: false,
+            modal,
+            sender,
+            receiver,
+        }
     }
 
     pub fn next_char(&mut self) {
-        if self.lines.len() > 0 {
+        if !self.lines.is_empty() {
             let line = &self.lines[self.cursor_position.line];
 
-            if self.cursor_position.char < line.len() {
-                self.cursor_position.char += 1;
+            if let Some(c) = line[self.cursor_position.char..].chars().next() {
+                self.cursor_position.char += c.len_utf8();
             } else {
                 if self.cursor_position.line + 1 < self.lines.len() {
                     self.cursor_position.line += 1;
@@ -78,9 +195,11 @@ impl TextEditor {
     }
 
     pub fn prev_char(&mut self) {
-        if self.lines.len() > 0 {
+        if !self.lines.is_empty() {
             if self.cursor_position.char > 0 {
-                self.cursor_position.char -= 1;
+                let line = &self.lines[self.cursor_position.line];
+                let c = line[..self.cursor_position.char].chars().next_back();
+                self.cursor_position.char -= c.map_or(1, char::len_utf8);
             } else {
                 if self.cursor_position.line > 0 {
                     self.cursor_position.line -= 1;
@@ -96,13 +215,14 @@ impl TextEditor {
             self.cursor_position.line += 1;
 
             let line = &self.lines[self.cursor_position.line];
-            if line.len() > 0 {
+            if !line.is_empty() {
                 if self.cursor_position.char > line.len() {
                     self.cursor_position.char = line.len();
                 }
             } else {
                 self.cursor_position.char = 0;
             }
+            self.snap_to_char_boundary();
         }
     }
 
@@ -111,19 +231,470 @@ impl TextEditor {
             self.cursor_position.line -= 1;
 
             let line = &self.lines[self.cursor_position.line];
-            if line.len() > 0 {
+            if !line.is_empty() {
                 if self.cursor_position.char > line.len() - 1 {
                     self.cursor_position.char = line.len() - 1;
                 }
             } else {
                 self.cursor_position.char = 0;
             }
+            self.snap_to_char_boundary();
+        }
+    }
+
+    fn snap_to_char_boundary(&mut self) {
+        let line = &self.lines[self.cursor_position.line];
+        while !line.is_char_boundary(self.cursor_position.char) {
+            self.cursor_position.char -= 1;
         }
     }
 
     pub fn save(&mut self) {
+        if self.changed_on_disk() {
+            self.prompt_for_disk_change();
+        } else {
+            self.write_file();
+        }
+    }
+
+    // Returns whether the file was written. Text the encoding cannot represent
+    // is never replaced silently: the user is offered UTF-8 instead.
+    pub fn write_file(&mut self) -> bool {
+        let (contents, had_errors) = self.file_contents();
+        if had_errors {
+            self.prompt_for_utf8_save();
+            return false;
+        }
+        if let Err(e) = fs::write(&self.file, contents) {
+            self.open_info_modal(format!("Could not save {}: {}", self.get_file_name(), e));
+            return false;
+        }
         self.file_saved = true;
-        let _ = fs::write(self.file.clone(), self.get_text());
+        self.disk_stamp = DiskStamp::read(&self.file);
+        true
+    }
+
+    fn prompt_for_utf8_save(&mut self) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(ConfirmationVariant::new(
+            format!(
+                "{} cannot represent some characters in {}. Save it as UTF-8?",
+                self.encoding.name(),
+                self.get_file_name()
+            ),
+            Box::new(move |_| {
+                sender.send(EditorTask::SaveAsUtf8).unwrap();
+            }),
+        )));
+    }
+
+    pub fn reload(&mut self) {
+        if self.file_saved {
+            self.reload_file();
+            return;
+        }
+
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(ConfirmationVariant::new(
+            format!("Discard changes to {} and reload?", self.get_file_name()),
+            Box::new(move |_| {
+                sender.send(EditorTask::Reload).unwrap();
+            }),
+        )));
+    }
+
+    fn reload_file(&mut self) {
+        if let Err(e) = self.set_path(self.file.clone()) {
+            self.open_info_modal(format!("Could not reload: {}", e));
+        }
+    }
+
+    fn changed_on_disk(&self) -> bool {
+        self.disk_stamp.is_some() && DiskStamp::read(&self.file) != self.disk_stamp
+    }
+
+    fn prompt_for_disk_change(&mut self) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(OptionsVariant::new(
+            format!("{} changed on disk.", self.get_file_name()),
+            vec![
+                "Reload".to_string(),
+                "Overwrite".to_string(),
+                "Cancel".to_string(),
+            ],
+            Box::new(move |index| {
+                sender.send(EditorTask::ResolveDiskChange(index)).unwrap();
+            }),
+        )));
+    }
+
+    pub fn prompt_for_goto_line(&mut self) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            format!("Go to line (1-{}):", self.lines.len()),
+            String::new(),
+            Box::new(move |answer| {
+                sender.send(EditorTask::GotoLine(answer)).unwrap();
+            }),
+        )));
+    }
+
+    pub fn prompt_for_search(&mut self) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            "Search:".to_string(),
+            self.search_query.clone(),
+            Box::new(move |answer| {
+                sender.send(EditorTask::Search(answer)).unwrap();
+            }),
+        )));
+    }
+
+    pub fn prompt_for_replace(&mut self) {
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            "Replace:".to_string(),
+            self.search_query.clone(),
+            Box::new(move |answer| {
+                sender.send(EditorTask::ReplaceSearch(answer)).unwrap();
+            }),
+        )));
+    }
+
+    fn prompt_for_replacement(&mut self, query: String) {
+        if query.is_empty() {
+            return;
+        }
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            format!("Replace {} with:", query),
+            String::new(),
+            Box::new(move |answer| {
+                sender
+                    .send(EditorTask::Replace(query.clone(), answer))
+                    .unwrap();
+            }),
+        )));
+    }
+
+    fn replace_all(&mut self, query: &str, replacement: &str) {
+        let count: usize = self
+            .lines
+            .iter()
+            .map(|line| line.matches(query).count())
+            .sum();
+        if count == 0 {
+            self.open_info_modal(format!("No matches for {}", query));
+            return;
+        }
+        self.push_undo(false);
+        for line in self.lines.iter_mut() {
+            if line.contains(query) {
+                *line = line.replace(query, replacement);
+            }
+        }
+        self.file_saved = false;
+        let line = &self.lines[self.cursor_position.line];
+        self.cursor_position.char = self.cursor_position.char.min(line.len());
+        self.snap_to_char_boundary();
+        self.open_info_modal(format!("Replaced {} occurrences of {}", count, query));
+    }
+
+    pub fn next_match(&mut self) {
+        self.goto_match(true);
+    }
+
+    pub fn prev_match(&mut self) {
+        self.goto_match(false);
+    }
+
+    fn goto_match(&mut self, forward: bool) {
+        if self.search_query.is_empty() {
+            return;
+        }
+        let matches: Vec<(usize, usize)> = self
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line, text)| {
+                text.match_indices(self.search_query.as_str())
+                    .map(move |(offset, _)| (line, offset))
+            })
+            .collect();
+        let cursor = (self.cursor_position.line, self.cursor_position.char);
+        let found = if forward {
+            matches.iter().find(|m| **m > cursor).or(matches.first())
+        } else {
+            matches
+                .iter()
+                .rev()
+                .find(|m| **m < cursor)
+                .or(matches.last())
+        };
+        match found {
+            Some(&(line, char)) => self.cursor_position = CursorPosition { line, char },
+            None => self.open_info_modal(format!("No matches for {}", self.search_query)),
+        }
+    }
+
+    fn goto_line(&mut self, line_number: usize) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let line = line_number.clamp(1, self.lines.len()) - 1;
+        let first_non_blank = self.lines[line]
+            .char_indices()
+            .find(|(_, c)| !c.is_whitespace())
+            .map_or(self.lines[line].len(), |(index, _)| index);
+        self.cursor_position = CursorPosition {
+            line,
+            char: first_non_blank,
+        };
+    }
+
+    fn remember_position(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        if let Ok(path) = self.file.canonicalize() {
+            self.remembered_positions
+                .retain(|(known, _)| *known != path);
+            self.remembered_positions
+                .push_front((path, self.cursor_position));
+            self.remembered_positions.truncate(MAX_REMEMBERED_POSITIONS);
+        }
+    }
+
+    fn restored_position(&self) -> CursorPosition {
+        let Ok(path) = self.file.canonicalize() else {
+            return CursorPosition::new();
+        };
+        let Some((_, position)) = self
+            .remembered_positions
+            .iter()
+            .find(|(known, _)| *known == path)
+        else {
+            return CursorPosition::new();
+        };
+
+        let line = position.line.min(self.lines.len().saturating_sub(1));
+        let line_str = &self.lines[line];
+        let mut char = position.char.min(line_str.len());
+        while !line_str.is_char_boundary(char) {
+            char -= 1;
+        }
+        CursorPosition { line, char }
+    }
+
+    fn open_info_modal(&mut self, message: String) {
+        self.modal = Modal::new(Box::new(InfoVariant::new(message)));
+    }
+
+    fn dispatch_on_task(&mut self, task: EditorTask) {
+        match task {
+            EditorTask::GotoLine(answer) => match answer.trim().parse::<usize>() {
+                Ok(line_number) => self.goto_line(line_number),
+                Err(_) => self.open_info_modal(format!("Invalid line number: {}", answer)),
+            },
+            EditorTask::ResolveDiskChange(index) => match index {
+                0 => self.reload_file(),
+                1 => {
+                    self.write_file();
+                }
+                _ => {}
+            },
+            EditorTask::Reload => self.reload_file(),
+            EditorTask::ReplaceSearch(query) => self.prompt_for_replacement(query),
+            EditorTask::Replace(query, replacement) => self.replace_all(&query, &replacement),
+            EditorTask::Search(query) => {
+                self.search_query = query;
+                self.next_match();
+            }
+            EditorTask::SetEncoding(label) => match Encoding::for_label(label.trim().as_bytes()) {
+                Some(encoding) => {
+                    self.forced_encoding = Some(encoding);
+                    self.reload_file();
+                }
+                None => self.open_info_modal(format!("Unknown encoding: {}", label)),
+            },
+            EditorTask::SaveAsUtf8 => {
+                self.encoding = UTF_8;
+                self.forced_encoding = None;
+                self.write_file();
+            }
+        }
+    }
+
+    pub fn prompt_for_encoding(&mut self) {
+        if !self.file_saved {
+            self.open_info_modal(
+                "Save or reload the file before changing its encoding".to_string(),
+            );
+            return;
+        }
+        let sender = self.sender.clone();
+        self.modal = Modal::new(Box::new(QuestionVariant::new(
+            "Reopen with encoding:".to_string(),
+            self.encoding.name().to_string(),
+            Box::new(move |answer| {
+                sender.send(EditorTask::SetEncoding(answer)).unwrap();
+            }),
+        )));
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            lines: self.lines.clone(),
+            cursor_position: self.cursor_position,
+        }
+    }
+
+    fn push_undo(&mut self, coalesce: bool) {
+        if !(coalesce && self.coalesce_inserts) {
+            self.undo_stack.push_back(self.snapshot());
+            if self.undo_stack.len() > self.undo_limit {
+                self.undo_stack.pop_front();
+            }
+        }
+        self.redo_stack.clear();
+        self.coalesce_inserts = coalesce;
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.lines = snapshot.lines;
+        self.cursor_position = snapshot.cursor_position;
+        self.file_saved = false;
+    }
+
+    pub fn undo(&mut self) {
+        let Some(snapshot) = self.undo_stack.pop_back() else {
+            return;
+        };
+        self.redo_stack.push(self.snapshot());
+        self.restore(snapshot);
+    }
+
+    pub fn redo(&mut self) {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return;
+        };
+        self.undo_stack.push_back(self.snapshot());
+        self.restore(snapshot);
+    }
+
+    pub fn indent(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        self.push_undo(false);
+        let indent = self.indent_unit();
+        if let Some(line) = self.lines.get_mut(self.cursor_position.line) {
+            line.insert_str(0, &indent);
+            self.cursor_position.char += indent.len();
+            self.file_saved = false;
+        }
+    }
+
+    fn indent_unit(&self) -> String {
+        if self.indent_with_tabs {
+            String::from("\t")
+        } else {
+            " ".repeat(self.tab_width)
+        }
+    }
+
+    pub fn dedent(&mut self) {
+        let can_dedent = self
+            .lines
+            .get(self.cursor_position.line)
+            .is_some_and(|line| line.starts_with([' ', '\t']));
+        if can_dedent {
+            self.push_undo(false);
+        }
+        if let Some(line) = self.lines.get_mut(self.cursor_position.line) {
+            let removed = if line.starts_with('\t') {
+                1
+            } else {
+                line.bytes()
+                    .take(self.tab_width)
+                    .take_while(|&b| b == b' ')
+                    .count()
+            };
+            if removed > 0 {
+                line.replace_range(..removed, "");
+                self.cursor_position.char = self.cursor_position.char.saturating_sub(removed);
+                self.file_saved = false;
+            }
+        }
+    }
+
+    fn comment_prefix(&self) -> &'static str {
+        let extension = self.file.extension().and_then(|e| e.to_str()).unwrap_or("");
+        COMMENT_PREFIXES
+            .iter()
+            .find(|(associated, _)| associated.eq_ignore_ascii_case(extension))
+            .map_or(DEFAULT_COMMENT_PREFIX, |(_, prefix)| prefix)
+    }
+
+    pub fn toggle_comment(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+        self.push_undo(false);
+        let prefix = self.comment_prefix();
+        let Some(line) = self.lines.get_mut(self.cursor_position.line) else {
+            return;
+        };
+        let indent = line.len() - line.trim_start().len();
+        let cursor = &mut self.cursor_position.char;
+
+        if line[indent..].starts_with(prefix) {
+            let mut end = indent + prefix.len();
+            if line[end..].starts_with(' ') {
+                end += 1;
+            }
+            line.replace_range(indent..end, "");
+            if *cursor > indent {
+                *cursor = cursor.saturating_sub(end - indent).max(indent);
+            }
+        } else {
+            let comment = format!("{} ", prefix);
+            line.insert_str(indent, &comment);
+            if *cursor >= indent {
+                *cursor += comment.len();
+            }
+        }
+        self.file_saved = false;
+    }
+
+    pub fn show_stats(&mut self) {
+        let text = self.get_text();
+        let line_breaks = self.lines.len().saturating_sub(1);
+        let (contents, _) = self.file_contents();
+        let bytes = contents.len();
+        let encoding = if self.lossy {
+            format!("{} (lossy)", self.encoding.name())
+        } else {
+            self.encoding.name().to_string()
+        };
+        let line_ending = if self.crlf { "CRLF" } else { "LF" };
+        self.open_info_modal(format!(
+            "Lines: {}\nWords: {}\nCharacters: {}\nBytes: {}\nLine endings: {}\nEncoding: {}",
+            line_breaks,
+            text.split_whitespace().count(),
+            text.chars().count(),
+            bytes,
+            line_ending,
+            encoding
+        ));
+    }
+
+    pub fn toggle_wrap(&mut self) {
+        self.soft_wrap = !self.soft_wrap;
+    }
+
+    pub fn toggle_line_numbers(&mut self) {
+        self.line_numbers = !self.line_numbers;
     }
 
     pub fn edit_mode(&mut self) {
@@ -145,29 +716,27 @@ impl TextEditor {
     }
 
     pub fn insert(&mut self, key_code: KeyCode) {
+        self.push_undo(matches!(key_code, KeyCode::Char(_)));
         self.file_saved = false;
         let line: &String = &self.lines[self.cursor_position.line];
         match key_code {
             KeyCode::Tab => {
-                self.lines[self.cursor_position.line].insert(self.cursor_position.char, ' ');
-                self.lines[self.cursor_position.line].insert(self.cursor_position.char, ' ');
-                self.lines[self.cursor_position.line].insert(self.cursor_position.char, ' ');
-                self.lines[self.cursor_position.line].insert(self.cursor_position.char, ' ');
-                self.next_char();
-                self.next_char();
-                self.next_char();
-                self.next_char();
+                let indent = self.indent_unit();
+                self.lines[self.cursor_position.line]
+                    .insert_str(self.cursor_position.char, &indent);
+                self.cursor_position.char += indent.len();
             }
+            KeyCode::Char(c) if self.auto_pair => self.insert_paired(c),
             KeyCode::Char(c) => {
                 self.lines[self.cursor_position.line].insert(self.cursor_position.char, c);
                 self.next_char();
             }
-            KeyCode::Backspace if line.len() > 0 && self.cursor_position.char >= 1 => {
-                let line = &mut self.lines[self.cursor_position.line];
-                line.remove(self.cursor_position.char - 1);
+            KeyCode::Backspace if !line.is_empty() && self.cursor_position.char >= 1 => {
                 self.prev_char();
+                let line = &mut self.lines[self.cursor_position.line];
+                line.remove(self.cursor_position.char);
             }
-            KeyCode::Delete if line.len() > 0 && self.cursor_position.char < line.len() => {
+            KeyCode::Delete if !line.is_empty() && self.cursor_position.char < line.len() => {
                 let line = &mut self.lines[self.cursor_position.line];
                 line.remove(self.cursor_position.char);
             }
@@ -215,46 +784,311 @@ impl TextEditor {
         }
     }
 
-    fn highlight_cursor<'a>(
-        &'a self,
-        (line_index, line_str): (usize, &'a str),
-        cp: CursorPosition,
-    ) -> Line {
-        let cursor_line_index = cp.line;
-        let char_index = cp.char;
-        if cursor_line_index == line_index && self.is_focused {
-            if char_index < line_str.len() {
-                let before = &line_str[..char_index];
-                let highlighted = &line_str[char_index..char_index + 1];
-                let after = &line_str[char_index + 1..];
-
-                Line::from(vec![
-                    Span::from(before),
-                    Span::styled(
-                        highlighted,
-                        Style::default().fg(Color::Black).bg(Color::White),
-                    ),
-                    Span::from(after),
-                ])
+    fn insert_paired(&mut self, c: char) {
+        let line = &mut self.lines[self.cursor_position.line];
+        let index = self.cursor_position.char;
+        let previous = line[..index].chars().next_back();
+        let next = line[index..].chars().next();
+
+        let closing = match c {
+            '(' => Some(')'),
+            '[' => Some(']'),
+            '{' => Some('}'),
+            '"' | '`' if !previous.is_some_and(char::is_alphanumeric) => Some(c),
+            _ => None,
+        };
+
+        if next == Some(c) && matches!(c, ')' | ']' | '}' | '"' | '`') {
+            self.next_char();
+            return;
+        }
+
+        line.insert(index, c);
+        if let Some(closing) = closing {
+            line.insert(index + c.len_utf8(), closing);
+        }
+        self.next_char();
+    }
+
+    fn highlight_line(
+        &self,
+        line_str: &str,
+        highlights: &[(usize, usize, Style)],
+    ) -> Line<'static> {
+        let style_at = |index: usize| {
+            highlights
+                .iter()
+                .find(|(start, end, _)| *start <= index && index < *end)
+                .map(|(_, _, style)| *style)
+        };
+
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut run = String::new();
+        let mut run_style = None;
+        let mut char_count = 0;
+        for (index, c) in line_str.chars().enumerate() {
+            let style = style_at(index);
+            if style != run_style && !run.is_empty() {
+                spans.push(Span::styled(
+                    std::mem::take(&mut run),
+                    run_style.unwrap_or_default(),
+                ));
+            }
+            run_style = style;
+            run.push(c);
+            char_count = index + 1;
+        }
+        if !run.is_empty() {
+            spans.push(Span::styled(run, run_style.unwrap_or_default()));
+        }
+        if let Some(style) = style_at(char_count) {
+            spans.push(Span::styled(" ", style));
+        }
+        Line::from(spans)
+    }
+
+    fn line_highlights(
+        &self,
+        index: usize,
+        line_str: &str,
+        columns: &[usize],
+        brackets: &[(usize, usize, Color)],
+    ) -> Vec<(usize, usize, Style)> {
+        if !self.is_focused {
+            return vec![];
+        }
+        let cursor = (index == self.cursor_position.line).then(|| self.cursor_columns(columns));
+        let mut cursor_style = Style::default().fg(Color::Black).bg(Color::White);
+        let mut highlights = Vec::new();
+        for (_, offset, color) in brackets.iter().filter(|(line, _, _)| *line == index) {
+            let char_index = line_str[..*offset].chars().count();
+            let cell = (columns[char_index], columns[char_index + 1]);
+            if cursor == Some(cell) {
+                cursor_style = cursor_style.fg(*color);
+            } else {
+                highlights.push((cell.0, cell.1, Style::default().bg(*color)));
+            }
+        }
+        if let Some((start, end)) = cursor {
+            highlights.push((start, end, cursor_style));
+        }
+        if !self.search_query.is_empty() {
+            let match_style = Style::default().fg(Color::Black).bg(Color::Yellow);
+            for (offset, found) in line_str.match_indices(self.search_query.as_str()) {
+                let start = line_str[..offset].chars().count();
+                let end = start + found.chars().count();
+                highlights.push((columns[start], columns[end], match_style));
+            }
+        }
+        highlights
+    }
+
+    fn bracket_highlights(&self) -> Vec<(usize, usize, Color)> {
+        let CursorPosition { line, char } = self.cursor_position;
+        let Some(text) = self.lines.get(line) else {
+            return vec![];
+        };
+        let at = text[char.min(text.len())..]
+            .chars()
+            .next()
+            .filter(|c| is_bracket(*c))
+            .map(|c| (char, c));
+        let before = text[..char.min(text.len())]
+            .char_indices()
+            .next_back()
+            .filter(|(_, c)| is_bracket(*c));
+        let Some((offset, bracket)) = at.or(before) else {
+            return vec![];
+        };
+
+        match self.find_matching_bracket(line, offset, bracket) {
+            Some((match_line, match_offset)) => vec![
+                (line, offset, Color::Magenta),
+                (match_line, match_offset, Color::Magenta),
+            ],
+            None => vec![(line, offset, Color::Red)],
+        }
+    }
+
+    fn find_matching_bracket(
+        &self,
+        line: usize,
+        offset: usize,
+        bracket: char,
+    ) -> Option<(usize, usize)> {
+        let (open, close, forward) = BRACKET_PAIRS.iter().find_map(|&(open, close)| {
+            if bracket == open {
+                Some((open, close, true))
+            } else if bracket == close {
+                Some((open, close, false))
             } else {
-                let highlighted = " ";
-                Line::from(vec![
-                    Span::from(line_str),
-                    Span::styled(
-                        highlighted,
-                        Style::default().fg(Color::Black).bg(Color::White),
-                    ),
-                ])
+                None
             }
+        })?;
+
+        let mut depth = 0;
+        let mut visit = |line_index: usize, (char_offset, c): (usize, char)| {
+            if c == bracket {
+                depth += 1;
+            } else if (forward && c == close) || (!forward && c == open) {
+                if depth == 0 {
+                    return Some((line_index, char_offset));
+                }
+                depth -= 1;
+            }
+            None
+        };
+
+        if forward {
+            for (line_index, text) in self.lines.iter().enumerate().skip(line) {
+                let mut chars = text
+                    .char_indices()
+                    .filter(|(i, _)| line_index > line || *i > offset);
+                if let Some(found) = chars.find_map(|entry| visit(line_index, entry)) {
+                    return Some(found);
+                }
+            }
+        } else {
+            for (line_index, text) in self.lines.iter().enumerate().take(line + 1).rev() {
+                let mut chars = text
+                    .char_indices()
+                    .rev()
+                    .filter(|(i, _)| line_index < line || *i < offset);
+                if let Some(found) = chars.find_map(|entry| visit(line_index, entry)) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    fn cursor_char_index(&self) -> usize {
+        self.lines.get(self.cursor_position.line).map_or(0, |line| {
+            line.char_indices()
+                .take_while(|(index, _)| *index < self.cursor_position.char)
+                .count()
+        })
+    }
+
+    fn cursor_columns(&self, columns: &[usize]) -> (usize, usize) {
+        let char_index = self.cursor_char_index();
+        match (columns.get(char_index), columns.get(char_index + 1)) {
+            (Some(&start), Some(&end)) => (start, end),
+            _ => {
+                let last = columns.last().copied().unwrap_or(0);
+                (last, last + 1)
+            }
+        }
+    }
+
+    fn cursor_display_column(&self) -> usize {
+        self.lines.get(self.cursor_position.line).map_or(0, |line| {
+            let (expanded, columns) = expand_tabs(line, self.tab_width);
+            let column = self.cursor_columns(&columns).0;
+            expanded
+                .chars()
+                .take(column)
+                .map(|c| c.width().unwrap_or(0))
+                .sum()
+        })
+    }
+
+    fn gutter_width(&self) -> u16 {
+        if self.line_numbers {
+            self.lines.len().max(1).to_string().len() as u16 + 1
         } else {
-            Line::from(line_str)
+            0
+        }
+    }
+
+    fn unwrapped_lines(&self) -> (Vec<Line<'static>>, Vec<Option<usize>>, usize) {
+        let brackets = self.bracket_highlights();
+        let lines = self
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line_str)| {
+                let (expanded, columns) = expand_tabs(line_str, self.tab_width);
+                let highlights = self.line_highlights(index, line_str, &columns, &brackets);
+                self.highlight_line(&expanded, &highlights)
+            })
+            .collect();
+        let line_numbers = (1..=self.lines.len()).map(Some).collect();
+        (lines, line_numbers, self.cursor_position.line)
+    }
+
+    fn wrapped_lines(&self, width: usize) -> (Vec<Line<'static>>, Vec<Option<usize>>, usize) {
+        let width = width.max(1);
+        let brackets = self.bracket_highlights();
+        let mut rows = Vec::new();
+        let mut line_numbers = Vec::new();
+        let mut cursor_row = 0;
+
+        for (index, line_str) in self.lines.iter().enumerate() {
+            let (expanded, columns) = expand_tabs(line_str, self.tab_width);
+            let highlights = self.line_highlights(index, line_str, &columns, &brackets);
+            let cursor =
+                (index == self.cursor_position.line).then(|| self.cursor_columns(&columns));
+            let offsets: Vec<usize> = expanded
+                .char_indices()
+                .map(|(offset, _)| offset)
+                .chain([expanded.len()])
+                .collect();
+            let char_count = offsets.len() - 1;
+            let row_starts = wrap_points(&expanded, width, cursor.map(|(start, _)| start));
+            let row_of = |index: usize| row_starts.iter().rposition(|&start| start <= index);
+
+            for (row, &start) in row_starts.iter().enumerate() {
+                let end = row_starts.get(row + 1).copied().unwrap_or(char_count);
+                let segment = &expanded[offsets[start]..offsets[end]];
+
+                if cursor.is_some_and(|(cursor_start, _)| row_of(cursor_start) == Some(row)) {
+                    cursor_row = rows.len();
+                }
+                let row_highlights: Vec<(usize, usize, Style)> = highlights
+                    .iter()
+                    .filter(|(highlight_start, _, _)| row_of(*highlight_start) == Some(row))
+                    .map(|(highlight_start, highlight_end, style)| {
+                        (highlight_start - start, highlight_end - start, *style)
+                    })
+                    .collect();
+                rows.push(self.highlight_line(segment, &row_highlights));
+                line_numbers.push((row == 0).then_some(index + 1));
+            }
+        }
+        (rows, line_numbers, cursor_row)
+    }
+
+    fn viewport_top(&self, cursor_row: usize, total_rows: usize, visible_rows: usize) -> usize {
+        if visible_rows == 0 {
+            return cursor_row;
         }
+        let margin = self.scroll_off.min((visible_rows - 1) / 2);
+        let mut top = self.scroll_top.get();
+        if cursor_row < top + margin {
+            top = cursor_row.saturating_sub(margin);
+        } else if cursor_row + margin >= top + visible_rows {
+            top = cursor_row + margin + 1 - visible_rows;
+        }
+        top = top
+            .min(total_rows.saturating_sub(visible_rows))
+            .min(cursor_row);
+        self.scroll_top.set(top);
+        top
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        !self.file_saved
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
     }
 
     pub fn get_file_name(&self) -> &str {
         self.file
             .components()
-            .last()
+            .next_back()
             .unwrap()
             .as_os_str()
             .to_str()
@@ -272,10 +1106,17 @@ impl TextEditor {
         }
 
         let filename = self.get_file_name();
+        let encoding = if self.binary {
+            String::from(" [hex]")
+        } else if self.encoding == UTF_8 {
+            String::new()
+        } else {
+            format!(" [{}]", self.encoding.name())
+        };
         if !self.file_saved {
-            format!("{}{}*", mode_str, filename)
+            format!("{}{}*{}", mode_str, filename, encoding)
         } else {
-            format!("{}{}", mode_str, filename)
+            format!("{}{}{}", mode_str, filename, encoding)
         }
     }
 
@@ -283,24 +1124,14 @@ impl TextEditor {
         self.lines.join("\n")
     }
 
+    // The bytes written on save, in the file's line endings and encoding.
+    fn file_contents(&self) -> (Vec<u8>, bool) {
+        let line_ending = if self.crlf { "\r\n" } else { "\n" };
+        encode(&self.lines.join(line_ending), self.encoding)
+    }
+
     fn draw_modal(&self, f: &mut Frame, area: Rect) {
-        let tmp = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Fill(1),
-                Constraint::Length(5),
-                Constraint::Fill(1),
-            ])
-            .split(area);
-
-        let popup_wrapper = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(25),
-                Constraint::Percentage(50),
-                Constraint::Percentage(25),
-            ])
-            .split(tmp[1])[1];
+        let popup_wrapper = centered_rect(area, area.width / 2, 5);
 
         let v_segments = Layout::default()
             .direction(Direction::Vertical)
@@ -337,43 +1168,62 @@ impl Drawable for TextEditor {
         if self.modal_open {
             self.draw_modal(f, area);
         } else {
-            let mut block = Block::bordered().title(self.get_title());
+            let mut block = Block::bordered()
+                .title(self.get_title())
+                .style(Style::new().white().on_black());
 
             if self.is_focused {
                 block = block.border_style(Color::Blue);
             }
 
-            let lines: Vec<Line> = self
-                .lines
-                .iter()
-                .enumerate()
-                .map(|(index, line_str)| {
-                    self.highlight_cursor((index, line_str), self.cursor_position)
-                })
-                .collect();
-
-            let mut p = Paragraph::new(lines)
-                .block(block)
-                .style(Style::new().white().on_black());
-
-            let x_margin = 2u16;
-            let y_margin = 2u16;
+            let inner = block.inner(area);
+            let gutter_width = self.gutter_width().min(inner.width);
+            let gutter_area = Rect::new(inner.x, inner.y, gutter_width, inner.height);
+            let text_area = Rect::new(
+                inner.x + gutter_width,
+                inner.y,
+                inner.width - gutter_width,
+                inner.height,
+            );
 
-            let x_scroll = if self.cursor_position.char as u16 + 1 + x_margin >= area.width {
-                self.cursor_position.char as u16 + 1 + x_margin - area.width
+            let (lines, line_numbers, cursor_row) = if self.soft_wrap {
+                self.wrapped_lines(text_area.width as usize)
             } else {
-                0
+                self.unwrapped_lines()
             };
 
-            let y_scroll = if self.cursor_position.line as u16 + 1 + y_margin >= area.height {
-                self.cursor_position.line as u16 + 1 + y_margin - area.height
-            } else {
+            let x_scroll = if self.soft_wrap {
                 0
+            } else {
+                (self.cursor_display_column() as u16 + 1).saturating_sub(text_area.width)
             };
+            let visible_rows = text_area.height as usize;
+            let top = self.viewport_top(cursor_row, lines.len(), visible_rows);
+            let visible_lines: Vec<Line> = lines.into_iter().skip(top).take(visible_rows).collect();
 
-            p = p.scroll((y_scroll, x_scroll));
+            f.render_widget(block, area);
+            f.render_widget(
+                Paragraph::new(visible_lines).scroll((0, x_scroll)),
+                text_area,
+            );
+
+            if self.line_numbers {
+                let width = gutter_width.saturating_sub(1) as usize;
+                let gutter: Vec<Line> = line_numbers
+                    .into_iter()
+                    .skip(top)
+                    .take(visible_rows)
+                    .map(|number| {
+                        let number = number.map_or(String::new(), |n| n.to_string());
+                        Line::from(format!("{:>width$} ", number)).dark_gray()
+                    })
+                    .collect();
+                f.render_widget(Paragraph::new(gutter), gutter_area);
+            }
 
-            f.render_widget(p, area);
+            if self.modal.is_open() {
+                self.modal.draw(f, area);
+            }
         }
     }
 }
@@ -381,6 +1231,9 @@ impl Drawable for TextEditor {
 impl Focusable for TextEditor {
     fn focus(&mut self) {
         self.is_focused = true;
+        if self.changed_on_disk() {
+            self.prompt_for_disk_change();
+        }
     }
 
     fn unfocus(&mut self) {
@@ -392,37 +1245,145 @@ impl Focusable for TextEditor {
     }
 }
 
-fn get_insertable_key_codes() -> Vec<KeyCode> {
-    let mut vec: Vec<KeyCode> = (32u8..=126u8).map(|c| KeyCode::Char(c as char)).collect();
-    vec.push(KeyCode::Backspace);
-    vec.push(KeyCode::Delete);
-    vec.push(KeyCode::Enter);
-    vec.push(KeyCode::Tab);
-    vec
+fn is_bracket(c: char) -> bool {
+    BRACKET_PAIRS
+        .iter()
+        .any(|&(open, close)| c == open || c == close)
+}
+
+fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    detector.guess(None, Utf8Detection::Allow)
+}
+
+// Also returns whether any character had to be replaced.
+fn encode(text: &str, encoding: &'static Encoding) -> (Vec<u8>, bool) {
+    // encoding_rs only decodes UTF-16, so it is written by hand with a BOM.
+    if encoding == UTF_16LE {
+        let bytes = [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        return (bytes, false);
+    }
+    if encoding == UTF_16BE {
+        let bytes = [0xFE, 0xFF]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect();
+        return (bytes, false);
+    }
+    let (bytes, _, had_errors) = encoding.encode(text);
+    (bytes.into_owned(), had_errors)
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    let prefix = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+    if prefix.contains(&0) {
+        return true;
+    }
+
+    let text = String::from_utf8_lossy(prefix);
+    let total = text.chars().count();
+    let non_text = text
+        .chars()
+        .filter(|&c| c == char::REPLACEMENT_CHARACTER || (c.is_control() && !c.is_whitespace()))
+        .count();
+    non_text * 10 > total * 3
+}
+
+// Char indices at which each wrapped row of `line` starts.
+fn wrap_points(line: &str, width: usize, cursor: Option<usize>) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut used = 0;
+    let mut char_count = 0;
+    for (index, c) in line.chars().enumerate() {
+        let char_width = c.width().unwrap_or(0);
+        if used > 0 && used + char_width > width {
+            starts.push(index);
+            used = 0;
+        }
+        used += char_width;
+        char_count = index + 1;
+    }
+    if cursor == Some(char_count) && used > 0 && used >= width {
+        starts.push(char_count);
+    }
+    starts
+}
+
+fn expand_tabs(line: &str, tab_width: usize) -> (String, Vec<usize>) {
+    let mut expanded = String::with_capacity(line.len());
+    let mut columns = Vec::with_capacity(line.len() + 1);
+    let mut column = 0;
+    let mut display_column = 0;
+    for c in line.chars() {
+        columns.push(column);
+        if c == '\t' {
+            let width = tab_width - display_column % tab_width;
+            expanded.extend(std::iter::repeat_n(' ', width));
+            column += width;
+            display_column += width;
+        } else {
+            expanded.push(c);
+            column += 1;
+            display_column += c.width().unwrap_or(0);
+        }
+    }
+    columns.push(column);
+    (expanded, columns)
 }
 
+const EDITING_COMMANDS: [&str; 8] = [
+    "text_editor.save",
+    "text_editor.replace",
+    "text_editor.insert_mode",
+    "text_editor.undo",
+    "text_editor.redo",
+    "text_editor.indent",
+    "text_editor.dedent",
+    "text_editor.toggle_comment",
+];
+
 fn is_insertable_key_code(key_code: KeyCode) -> bool {
-    get_insertable_key_codes().contains(&key_code)
+    match key_code {
+        KeyCode::Char(c) => !c.is_control(),
+        KeyCode::Backspace | KeyCode::Delete | KeyCode::Enter | KeyCode::Tab => true,
+        _ => false,
+    }
 }
 
 impl InputHandler for TextEditor {
-    fn handle_input(&mut self, key_code: KeyCode) -> bool {
+    fn handle_input(&mut self, key: Key) -> bool {
         if self.modal_open {
-            if key_code == KeyCode::Char('y') {
+            if key.code == KeyCode::Char('y') {
                 self.modal_open = false;
                 self.save();
-            } else if key_code == KeyCode::Char('n') {
+            } else if key.code == KeyCode::Char('n') {
                 self.modal_open = false;
                 let _ = self.set_path(self.file.clone());
             }
-            false
+            self.modal.is_open()
+        } else if self.modal.is_open() {
+            self.modal.handle_input(key.code);
+            if let Ok(task) = self.receiver.try_recv() {
+                self.dispatch_on_task(task);
+            }
+            true
         } else {
             match self.mode {
-                Mode::Edit if is_insertable_key_code(key_code) => {
-                    self.insert(key_code);
+                Mode::Edit if key.is_plain() && is_insertable_key_code(key.code) => {
+                    self.insert(key.code);
                     true
                 }
-                Mode::View | Mode::Edit => self.handle_command(key_code),
+                Mode::View | Mode::Edit => {
+                    self.coalesce_inserts = false;
+                    self.handle_command(key)
+                }
             }
         }
     }
@@ -432,8 +1393,12 @@ impl CommandHandler for TextEditor {
     fn get_name(&self) -> &'static str {
         "text_editor"
     }
-    fn get_commands(&self) -> Vec<Command<TextEditor>> {
-        vec![
+
+    fn is_command_enabled(&self, command_id: &str) -> bool {
+        !((self.read_only || self.binary) && EDITING_COMMANDS.contains(&command_id))
+    }
+    fn get_commands(&self) -> &'static [Command<TextEditor>] {
+        &[
             Command {
                 id: "text_editor.next_char",
                 name: "Next char",
@@ -464,6 +1429,81 @@ impl CommandHandler for TextEditor {
                 name: "Edit",
                 func: as_command!(TextEditor, edit_mode),
             },
+            Command {
+                id: "text_editor.undo",
+                name: "Undo",
+                func: as_command!(TextEditor, undo),
+            },
+            Command {
+                id: "text_editor.redo",
+                name: "Redo",
+                func: as_command!(TextEditor, redo),
+            },
+            Command {
+                id: "text_editor.goto_line",
+                name: "Go to line",
+                func: as_command!(TextEditor, prompt_for_goto_line),
+            },
+            Command {
+                id: "text_editor.search",
+                name: "Search",
+                func: as_command!(TextEditor, prompt_for_search),
+            },
+            Command {
+                id: "text_editor.next_match",
+                name: "Next match",
+                func: as_command!(TextEditor, next_match),
+            },
+            Command {
+                id: "text_editor.prev_match",
+                name: "Prev match",
+                func: as_command!(TextEditor, prev_match),
+            },
+            Command {
+                id: "text_editor.replace",
+                name: "Replace",
+                func: as_command!(TextEditor, prompt_for_replace),
+            },
+            Command {
+                id: "text_editor.encoding",
+                name: "Encoding",
+                func: as_command!(TextEditor, prompt_for_encoding),
+            },
+            Command {
+                id: "text_editor.reload",
+                name: "Reload",
+                func: as_command!(TextEditor, reload),
+            },
+            Command {
+                id: "text_editor.indent",
+                name: "Indent",
+                func: as_command!(TextEditor, indent),
+            },
+            Command {
+                id: "text_editor.dedent",
+                name: "Dedent",
+                func: as_command!(TextEditor, dedent),
+            },
+            Command {
+                id: "text_editor.toggle_comment",
+                name: "Comment",
+                func: as_command!(TextEditor, toggle_comment),
+            },
+            Command {
+                id: "text_editor.stats",
+                name: "Stats",
+                func: as_command!(TextEditor, show_stats),
+            },
+            Command {
+                id: "text_editor.toggle_wrap",
+                name: "Wrap",
+                func: as_command!(TextEditor, toggle_wrap),
+            },
+            Command {
+                id: "text_editor.toggle_line_numbers",
+                name: "Line numbers",
+                func: as_command!(TextEditor, toggle_line_numbers),
+            },
             Command {
                 id: "text_editor.go_back",
                 name: "Go back",
@@ -475,14 +1515,450 @@ impl CommandHandler for TextEditor {
 
 impl Editor for TextEditor {
     fn set_path(&mut self, path: PathBuf) -> Result<()> {
+        self.remember_position();
+        if path != self.file {
+            self.forced_encoding = None;
+        }
         self.file = path;
 
-        let mut text = fs::read_to_string(&self.file).context("Unable to read file")?;
-        text = text.replace("\t", "    ").replace("\r", "");
-        self.lines = text.split("\n").map(|str| String::from(str)).collect();
-        self.cursor_position = CursorPosition::new();
+        let bytes = fs::read(&self.file).context("Unable to read file")?;
+        let encoding = self
+            .forced_encoding
+            .or_else(|| Encoding::for_bom(&bytes).map(|(encoding, _)| encoding));
+        self.binary = encoding.is_none() && is_binary(&bytes);
+        if self.binary {
+            let preview_len = bytes.len().min(MAX_PREVIEW_BYTES as usize);
+            self.lines = hex_dump(&bytes[..preview_len]);
+            self.mode = Mode::View;
+            self.encoding = UTF_8;
+            self.lossy = false;
+            self.crlf = false;
+            self.cursor_position = CursorPosition::new();
+            self.file_saved = true;
+            self.undo_stack.clear();
+            self.redo_stack.clear();
+            self.disk_stamp = DiskStamp::read(&self.file);
+            return Ok(());
+        }
+        let encoding = encoding.unwrap_or_else(|| detect_encoding(&bytes));
+        let (text, _, lossy) = encoding.decode(&bytes);
+        self.encoding = encoding;
+        self.lossy = lossy;
+        self.crlf = text.contains("\r\n");
+        let text = text.replace('\r', "");
+        self.lines = text.split("\n").map(String::from).collect();
+        self.cursor_position = self.restored_position();
         self.file_saved = true;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.coalesce_inserts = false;
+        self.disk_stamp = DiskStamp::read(&self.file);
 
         Ok(())
     }
+
+    fn get_commands_data(&self) -> Vec<(&'static str, &'static str)> {
+        self.get_commands()
+            .iter()
+            .filter(|c| self.is_command_enabled(c.id))
+            .map(|c| (c.id, c.name))
+            .collect()
+    }
+
+    fn modal_open(&self) -> bool {
+        self.modal_open
+    }
+
+    fn unsaved_files(&self) -> Vec<String> {
+        if self.has_unsaved_changes() {
+            vec![self.get_file_name().to_string()]
+        } else {
+            vec![]
+        }
+    }
+
+    fn save_all(&mut self) {
+        if self.has_unsaved_changes() {
+            self.write_file();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::WINDOWS_1252;
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+    use tempfile::TempDir;
+
+    fn open(contents: &[u8]) -> (TempDir, TextEditor) {
+        open_with(contents, &Config::default())
+    }
+
+    fn open_with(contents: &[u8], config: &Config) -> (TempDir, TextEditor) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, contents).unwrap();
+        let mut editor = TextEditor::new(config);
+        editor.set_path(path).unwrap();
+        (dir, editor)
+    }
+
+    fn render(editor: &TextEditor, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| editor.draw(f, f.size())).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    #[test]
+    fn expand_tabs_maps_chars_to_tab_stops() {
+        let (expanded, columns) = expand_tabs("a\tb\t\tc", 4);
+        assert_eq!(expanded, "a   b       c");
+        assert_eq!(columns, vec![0, 1, 4, 5, 8, 12, 13]);
+    }
+
+    #[test]
+    fn cursor_after_leading_tabs_is_drawn_at_the_tab_stop() {
+        let config = Config {
+            line_numbers: false,
+            ..Config::default()
+        };
+        let (_dir, mut editor) = open_with(b"\t\tx", &config);
+        editor.focus();
+        editor.cursor_position.char = 2;
+
+        let buffer = render(&editor, 20, 4);
+        let cell = buffer.get(1 + 8, 1);
+        assert_eq!(cell.symbol(), "x");
+        assert_eq!(cell.bg, Color::White);
+    }
+
+    fn change_on_disk(editor: &TextEditor, contents: &[u8]) {
+        fs::write(&editor.file, contents).unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&editor.file)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+    }
+
+    #[test]
+    fn saving_over_an_external_change_prompts_instead_of_writing() {
+        let (_dir, mut editor) = open(b"original");
+        editor.lines[0] = String::from("mine");
+        editor.file_saved = false;
+        change_on_disk(&editor, b"theirs");
+
+        editor.save();
+
+        assert!(editor.modal.is_open());
+        assert_eq!(fs::read(&editor.file).unwrap(), b"theirs");
+
+        editor.handle_input(KeyCode::Char('1').into());
+        assert_eq!(editor.lines, vec!["theirs"]);
+        assert!(!editor.changed_on_disk());
+    }
+
+    #[test]
+    fn nul_bytes_mark_a_file_as_binary() {
+        assert!(is_binary(b"text\0more text"));
+    }
+
+    #[test]
+    fn plain_and_multibyte_text_is_not_binary() {
+        assert!(!is_binary(b"fn main() {\n\tprintln!();\n}\n"));
+        assert!(!is_binary("café 🦀 test".as_bytes()));
+        assert!(!is_binary(b""));
+    }
+
+    #[test]
+    fn a_single_invalid_byte_does_not_make_text_binary() {
+        assert!(!is_binary(b"mostly fine text with one \xff byte in it"));
+    }
+
+    #[test]
+    fn mostly_invalid_or_control_bytes_are_binary() {
+        assert!(is_binary(&[0xff, 0xfe, 0x80, 0x81, b'a', 0x90, 0x91]));
+        assert!(is_binary(&[0x01, 0x02, 0x03, b'a', 0x04, 0x05]));
+    }
+
+    #[test]
+    fn binary_files_open_as_a_read_only_hex_dump() {
+        let (_dir, mut editor) = open(&[0x00, 0x01, b'A', 0xff]);
+        assert!(editor.binary);
+        assert_eq!(editor.lines, hex_dump(&[0x00, 0x01, b'A', 0xff]));
+        assert!(!editor.is_command_enabled("text_editor.insert_mode"));
+        assert!(editor.is_command_enabled("text_editor.next_line"));
+
+        editor.handle_input(KeyCode::Char('i').into());
+        assert!(editor.mode == Mode::View);
+    }
+
+    fn screen(buffer: &Buffer) -> String {
+        let area = buffer.area;
+        (area.top()..area.bottom())
+            .map(|y| {
+                (area.left()..area.right())
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn stats_count_the_bytes_of_the_file_on_disk() {
+        let latin1 = b"Le caf\xe9 est tr\xe8s bon.\r\nLa cr\xe8me br\xfbl\xe9e aussi.\r\n";
+        let (_dir, mut editor) = open(latin1);
+        assert_eq!(editor.encoding, WINDOWS_1252);
+
+        editor.show_stats();
+
+        let screen = screen(&render(&editor, 60, 20));
+        assert!(
+            screen.contains(&format!("Bytes: {}", latin1.len())),
+            "{screen}"
+        );
+        assert!(screen.contains("Characters: 45"), "{screen}");
+        assert!(screen.contains("Line endings: CRLF"), "{screen}");
+    }
+
+    #[test]
+    fn indent_adds_one_level_and_undoes_in_one_step() {
+        let (_dir, mut editor) = open(b"a\nb");
+        editor.cursor_position.char = 1;
+
+        editor.indent();
+        assert_eq!(editor.lines, ["    a", "b"]);
+        assert_eq!(editor.cursor_position.char, 5);
+        assert!(editor.has_unsaved_changes());
+
+        editor.undo();
+        assert_eq!(editor.lines, ["a", "b"]);
+    }
+
+    #[test]
+    fn indent_inserts_a_tab_when_configured() {
+        let config = Config {
+            indent_with_tabs: true,
+            ..Config::default()
+        };
+        let (_dir, mut editor) = open_with(b"a", &config);
+
+        editor.indent();
+        editor.insert(KeyCode::Tab);
+
+        assert_eq!(editor.lines, ["\t\ta"]);
+        assert_eq!(editor.cursor_position.char, 2);
+    }
+
+    #[test]
+    fn dedent_removes_at_most_one_level_of_whitespace() {
+        let (_dir, mut editor) = open(b"  a\n      b\n\t\tc\nd");
+
+        editor.dedent();
+        assert_eq!(editor.lines[0], "a");
+
+        editor.cursor_position.line = 1;
+        editor.dedent();
+        assert_eq!(editor.lines[1], "  b");
+
+        editor.cursor_position.line = 2;
+        editor.dedent();
+        assert_eq!(editor.lines[2], "\tc");
+
+        editor.cursor_position.line = 3;
+        editor.dedent();
+        assert_eq!(editor.lines[3], "d");
+    }
+
+    fn type_text(editor: &mut TextEditor, text: &str) {
+        for c in text.chars() {
+            editor.insert(KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn opening_brackets_and_quotes_insert_their_pair() {
+        let (_dir, mut editor) = open(b"");
+
+        type_text(&mut editor, "f(");
+        assert_eq!(editor.lines[0], "f()");
+        assert_eq!(editor.cursor_position.char, 2);
+
+        type_text(&mut editor, "[{\"");
+        assert_eq!(editor.lines[0], "f([{\"\"}])");
+        assert_eq!(editor.cursor_position.char, 5);
+    }
+
+    #[test]
+    fn closing_characters_type_over_the_pair() {
+        let (_dir, mut editor) = open(b"");
+
+        type_text(&mut editor, "(x)");
+        assert_eq!(editor.lines[0], "(x)");
+        assert_eq!(editor.cursor_position.char, 3);
+
+        type_text(&mut editor, " `y`");
+        assert_eq!(editor.lines[0], "(x) `y`");
+    }
+
+    #[test]
+    fn a_quote_after_a_word_is_not_paired() {
+        let (_dir, mut editor) = open(b"");
+
+        type_text(&mut editor, "don\"t");
+        assert_eq!(editor.lines[0], "don\"t");
+    }
+
+    #[test]
+    fn auto_pair_can_be_turned_off() {
+        let config = Config {
+            auto_pair: false,
+            ..Config::default()
+        };
+        let (_dir, mut editor) = open_with(b"", &config);
+
+        type_text(&mut editor, "f(x)");
+        assert_eq!(editor.lines[0], "f(x)");
+    }
+
+    #[test]
+    fn viewport_keeps_the_scroll_off_margin_near_the_bottom() {
+        let (_dir, editor) = open(b"");
+
+        assert_eq!(editor.viewport_top(0, 100, 10), 0);
+        assert_eq!(editor.viewport_top(6, 100, 10), 0);
+        assert_eq!(editor.viewport_top(7, 100, 10), 1);
+        assert_eq!(editor.viewport_top(20, 100, 10), 14);
+        assert_eq!(editor.viewport_top(16, 100, 10), 13);
+    }
+
+    #[test]
+    fn viewport_margin_is_clamped_on_short_files_and_small_views() {
+        let (_dir, editor) = open(b"");
+
+        assert_eq!(editor.viewport_top(98, 100, 10), 90);
+        assert_eq!(editor.viewport_top(99, 100, 10), 90);
+        assert_eq!(editor.viewport_top(3, 5, 10), 0);
+
+        editor.scroll_top.set(0);
+        assert_eq!(editor.viewport_top(4, 100, 3), 3);
+    }
+
+    #[test]
+    fn non_ascii_characters_are_inserted_in_edit_mode() {
+        let (_dir, mut editor) = open(b"ab");
+        editor.edit_mode();
+        editor.cursor_position.char = 1;
+
+        for c in ['é', '🦀', '漢'] {
+            assert!(editor.handle_input(KeyCode::Char(c).into()));
+        }
+
+        assert_eq!(editor.lines[0], "aé🦀漢b");
+        assert_eq!(editor.cursor_position.char, "aé🦀漢".len());
+    }
+
+    #[test]
+    fn wrap_points_count_double_width_characters() {
+        assert_eq!(wrap_points("漢字かな", 5, None), [0, 2]);
+        assert_eq!(wrap_points("漢字かな", 3, None), [0, 1, 2, 3]);
+        assert_eq!(wrap_points("漢字かな", 4, Some(4)), [0, 2, 4]);
+    }
+
+    #[test]
+    fn latin1_files_round_trip_through_a_save() {
+        let latin1 = b"Le caf\xe9 est tr\xe8s bon.\r\nLa cr\xe8me br\xfbl\xe9e aussi.\r\n";
+        let (_dir, mut editor) = open(latin1);
+        assert_eq!(editor.encoding, WINDOWS_1252);
+        assert_eq!(editor.lines[0], "Le café est très bon.");
+
+        assert!(editor.write_file());
+        assert_eq!(fs::read(&editor.file).unwrap(), latin1);
+
+        editor.lines[1] = String::from("Là crème brûlée aussi.");
+        editor.file_saved = false;
+        assert!(editor.write_file());
+        assert_eq!(
+            fs::read(&editor.file).unwrap(),
+            b"Le caf\xe9 est tr\xe8s bon.\r\nL\xe0 cr\xe8me br\xfbl\xe9e aussi.\r\n"
+        );
+    }
+
+    #[test]
+    fn unencodable_characters_offer_a_utf8_save_instead_of_replacing_them() {
+        let latin1 = b"Le caf\xe9 est tr\xe8s bon.\nLa cr\xe8me br\xfbl\xe9e aussi.\n";
+        let (_dir, mut editor) = open(latin1);
+        editor.lines[0].push_str(" 🦀");
+        editor.file_saved = false;
+
+        assert!(!editor.write_file());
+        assert!(editor.modal.is_open());
+        assert!(editor.has_unsaved_changes());
+        assert_eq!(fs::read(&editor.file).unwrap(), latin1);
+
+        editor.handle_input(KeyCode::Char('y').into());
+        assert_eq!(editor.encoding, UTF_8);
+        assert!(!editor.has_unsaved_changes());
+        assert_eq!(
+            fs::read_to_string(&editor.file).unwrap(),
+            "Le café est très bon. 🦀\nLa crème brûlée aussi.\n"
+        );
+    }
+
+    #[test]
+    fn failed_writes_are_reported_and_leave_the_file_unsaved() {
+        let (dir, mut editor) = open(b"text");
+        editor.file = dir.path().join("missing").join("file.txt");
+        editor.file_saved = false;
+
+        assert!(!editor.write_file());
+        assert!(editor.modal.is_open());
+        assert!(editor.has_unsaved_changes());
+    }
+
+    #[test]
+    fn the_cursor_walks_multibyte_characters_one_at_a_time() {
+        let line = "café 🦀 test";
+        let (_dir, mut editor) = open(line.as_bytes());
+        editor.focus();
+        let boundaries: Vec<usize> = line
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([line.len()])
+            .collect();
+
+        for &boundary in &boundaries[1..] {
+            editor.next_char();
+            assert_eq!(editor.cursor_position.char, boundary);
+            render(&editor, 40, 4);
+        }
+        editor.next_char();
+        assert_eq!(editor.cursor_position.char, line.len());
+
+        for &boundary in boundaries[..boundaries.len() - 1].iter().rev() {
+            editor.prev_char();
+            assert_eq!(editor.cursor_position.char, boundary);
+            render(&editor, 40, 4);
+        }
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_whole_multibyte_characters() {
+        let (_dir, mut editor) = open("café 🦀 test".as_bytes());
+        editor.edit_mode();
+
+        editor.cursor_position.char = "café 🦀".len();
+        editor.handle_input(KeyCode::Backspace.into());
+        assert_eq!(editor.lines[0], "café  test");
+        assert_eq!(editor.cursor_position.char, "café ".len());
+
+        editor.cursor_position.char = "caf".len();
+        editor.handle_input(KeyCode::Delete.into());
+        assert_eq!(editor.lines[0], "caf  test");
+        assert_eq!(editor.cursor_position.char, "caf".len());
+    }
 }